@@ -0,0 +1,525 @@
+//! Reads source files straight out of `.zip`, `.tar`, `.tar.gz` and `.tgz` archives, without
+//! extracting them.
+//!
+//! Only what source tarballs need is implemented: stored and deflated zip entries, and regular
+//! files in ustar/GNU tars, GNU long names and pax `path` records included. Nothing is held in
+//! memory past the `--max-file-size` limit, and decompressed data is checked against its CRC-32.
+
+use std::path::{Component, Path, PathBuf};
+use crate::error::ParseError;
+
+/// A regular file inside an archive.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The entry's path inside the archive, with `.`, `..` and root components dropped.
+    pub path: PathBuf,
+    pub data: Vec<u8>,
+}
+
+/// Whether `path` names an archive `pars` can read, judging by its extension.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_ascii_lowercase();
+    [".zip", ".tar", ".tar.gz", ".tgz"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Reads the regular files of the archive at `path` whose entry path passes `wanted`, in archive
+/// order. The format comes from the content: zip, gzip-compressed tar, or plain tar.
+///
+/// The archive, and a compressed tar once decompressed, may be at most `limit` bytes. Zip entries
+/// larger than that are skipped with a warning.
+pub fn read_archive(path: &Path, limit: usize, wanted: impl Fn(&Path) -> bool) -> Result<Vec<Entry>, ParseError> {
+    let size = std::fs::metadata(path)?.len() as usize;
+    if size > limit {
+        return Err(ParseError::FileTooLarge(format!("{} bytes, over the {} byte limit (--max-file-size)", size, limit)));
+    }
+    let bytes = std::fs::read(path)?;
+    let entries = if bytes.starts_with(b"PK") {
+        zip_entries(&bytes, limit, &wanted)
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        gunzip(&bytes, limit).and_then(|tar| tar_entries(&tar, &wanted))
+    } else {
+        tar_entries(&bytes, &wanted)
+    };
+    entries.map_err(|e| ParseError::InvalidArchive(format!("{}: {}", path.display(), e)))
+}
+
+/// Keeps only the normal components of an entry name, so `./src/a.py` is `src/a.py` and a
+/// hostile `../../a.py` can't point outside the archive (`--output-dir` writes below it).
+fn entry_path(name: &str) -> PathBuf {
+    Path::new(name)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+/// The text of a NUL-padded header field.
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&byte| byte == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn octal(field: &[u8]) -> Option<usize> {
+    let text = c_string(field);
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(text, 8).ok()
+}
+
+/// The `path` record of a pax extended header (`30 path=some/long/name.py\n`).
+fn pax_path(body: &[u8]) -> Option<String> {
+    let mut rest = body;
+    while let Some(space) = rest.iter().position(|&byte| byte == b' ') {
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?;
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Some(value) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+fn tar_entries(data: &[u8], wanted: &impl Fn(&Path) -> bool) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    // Set by a GNU `L` or pax `x` header for the entry that follows it.
+    let mut long_name = None;
+    let mut offset = 0;
+
+    while let Some(header) = data.get(offset..offset + 512) {
+        // The archive ends with two zero blocks.
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        let size = octal(&header[124..136]).ok_or("bad entry size")?;
+        let start = offset + 512;
+        let body = data.get(start..start + size).ok_or("truncated entry")?;
+        offset = start + size.div_ceil(512) * 512;
+
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = c_string(&header[..100]);
+            let prefix = c_string(&header[345..500]);
+            if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                format!("{}/{}", prefix, name)
+            } else {
+                name
+            }
+        });
+        match header[156] {
+            b'0' | 0 => {
+                let path = entry_path(&name);
+                if wanted(&path) {
+                    entries.push(Entry { path, data: body.to_vec() });
+                }
+            }
+            b'L' => long_name = Some(c_string(body)),
+            b'x' => long_name = pax_path(body),
+            // Directories, links, devices and global pax headers hold no source.
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<usize, String> {
+    let bytes = data.get(at..at + 2).ok_or("truncated zip header")?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<usize, String> {
+    let bytes = data.get(at..at + 4).ok_or("truncated zip header")?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Walks the central directory, which (unlike the local headers) always records entry sizes.
+fn zip_entries(data: &[u8], limit: usize, wanted: &impl Fn(&Path) -> bool) -> Result<Vec<Entry>, String> {
+    // The end-of-central-directory record is the last thing in the file, before a comment of up
+    // to 64 KiB.
+    let earliest = data.len().saturating_sub(22 + u16::MAX as usize);
+    let end = (earliest..=data.len().saturating_sub(22))
+        .rev()
+        .find(|&at| data[at..].starts_with(b"PK\x05\x06"))
+        .ok_or("no zip central directory")?;
+    let count = u16_at(data, end + 10)?;
+    let mut at = u32_at(data, end + 16)?;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        if !data.get(at..).is_some_and(|rest| rest.starts_with(b"PK\x01\x02")) {
+            return Err("bad central directory entry".to_string());
+        }
+        let flags = u16_at(data, at + 8)?;
+        let method = u16_at(data, at + 10)?;
+        let crc = u32_at(data, at + 16)? as u32;
+        let compressed = u32_at(data, at + 20)?;
+        let size = u32_at(data, at + 24)?;
+        let name_len = u16_at(data, at + 28)?;
+        let local = u32_at(data, at + 42)?;
+        let name = data.get(at + 46..at + 46 + name_len).ok_or("truncated entry name")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + name_len + u16_at(data, at + 30)? + u16_at(data, at + 32)?;
+
+        let path = entry_path(&name);
+        if name.ends_with('/') || !wanted(&path) {
+            continue;
+        }
+        if flags & 1 != 0 || !matches!(method, 0 | 8) {
+            eprintln!("Skipping {}: encrypted or compressed with method {}", name, method);
+            continue;
+        }
+        if size > limit {
+            eprintln!("Skipping {}: {} bytes, over the {} byte limit (--max-file-size)", name, size, limit);
+            continue;
+        }
+        let start = local + 30 + u16_at(data, local + 26)? + u16_at(data, local + 28)?;
+        let raw = data.get(start..start + compressed).ok_or("truncated entry")?;
+        // Inflating stops at the recorded size, so a lying header can't make it run on.
+        let data = if method == 8 { inflate(raw, size)?.0 } else { raw.to_vec() };
+        if data.len() != size || crc32(&data) != crc {
+            return Err(format!("{}: size or CRC-32 doesn't match its header", name));
+        }
+        entries.push(Entry { path, data });
+    }
+    Ok(entries)
+}
+
+/// Strips the gzip header (RFC 1952), inflates the first member to at most `limit` bytes and
+/// checks it against the trailer.
+fn gunzip(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+
+    if data.get(2) != Some(&8) {
+        return Err("gzip stream isn't deflated".to_string());
+    }
+    let flags = data.get(3).copied().ok_or("truncated gzip header")?;
+    let mut at = 10;
+    if flags & FEXTRA != 0 {
+        at += 2 + u16_at(data, at)?;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            at += data.get(at..).and_then(|rest| rest.iter().position(|&byte| byte == 0)).ok_or("truncated gzip header")? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        at += 2;
+    }
+    let (out, used) = inflate(data.get(at..).ok_or("truncated gzip header")?, limit)?;
+    let trailer = at + used;
+    if u32_at(data, trailer).map_err(|_| "truncated gzip trailer")? as u32 != crc32(&out)
+        || u32_at(data, trailer + 4)? as u32 != out.len() as u32
+    {
+        return Err("gzip CRC-32 or size doesn't match the data".to_string());
+    }
+    Ok(out)
+}
+
+/// CRC-32 (IEEE, as zip and gzip use it) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// Reads a deflate stream least significant bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, n: u32) -> Result<usize, String> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or("truncated deflate stream")?;
+            self.pos += 1;
+            self.buf |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Ok(value as usize)
+    }
+}
+
+/// A canonical Huffman code: how many codes each length has, and the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate().filter(|(_, len)| **len != 0) {
+            symbols[offsets[len as usize] as usize] = symbol as u16;
+            offsets[len as usize] += 1;
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<usize, String> {
+        // Codes of one length are consecutive; `first` is the first code of the current length
+        // and `index` the position of its symbol.
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)?;
+            let count = count as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first] as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// The order code length code lengths are stored in.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses a raw deflate stream (RFC 1951), failing once it would exceed `limit` bytes.
+/// Returns the data and how many bytes of `data` the stream took up.
+fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), String> {
+    let mut bits = Bits { data, pos: 0, buf: 0, count: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                // Stored blocks start on a byte boundary; the partial byte left is padding.
+                bits.buf = 0;
+                bits.count = 0;
+                let len = u16_at(data, bits.pos)?;
+                let start = bits.pos + 4;
+                if out.len() + len > limit {
+                    return Err(over_limit(limit));
+                }
+                out.extend_from_slice(data.get(start..start + len).ok_or("truncated stored block")?);
+                bits.pos = start + len;
+            }
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut bits, &mut out, limit, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, limit, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type".to_string()),
+        }
+        if last {
+            // Whole bytes still buffered belong to what follows the stream.
+            return Ok((out, bits.pos - bits.count as usize / 8));
+        }
+    }
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.take(5)? + 257;
+    let distance_count = bits.take(5)? + 1;
+    let code_count = bits.take(4)? + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[i] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let total = literal_count + distance_count;
+    let mut lengths = Vec::with_capacity(total);
+    while lengths.len() < total {
+        let (len, repeat) = match code_lengths.decode(bits)? {
+            len @ 0..=15 => (len as u8, 1),
+            16 => (*lengths.last().ok_or("repeat with no previous length")?, 3 + bits.take(2)?),
+            17 => (0, 3 + bits.take(3)?),
+            18 => (0, 11 + bits.take(7)?),
+            _ => return Err("invalid code length".to_string()),
+        };
+        lengths.extend(std::iter::repeat_n(len, repeat));
+    }
+    if lengths.len() > total {
+        return Err("code lengths overrun".to_string());
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn over_limit(limit: usize) -> String {
+    format!("decompresses to over {} bytes (--max-file-size)", limit)
+}
+
+fn inflate_block(bits: &mut Bits, out: &mut Vec<u8>, limit: usize, literals: &Huffman, distances: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        if symbol < 256 {
+            if out.len() == limit {
+                return Err(over_limit(limit));
+            }
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let i = symbol - 257;
+        if i >= LENGTH_BASE.len() {
+            return Err("invalid length code".to_string());
+        }
+        let len = LENGTH_BASE[i] + bits.take(LENGTH_EXTRA[i])?;
+        let d = distances.decode(bits)?;
+        if d >= DIST_BASE.len() {
+            return Err("invalid distance code".to_string());
+        }
+        let distance = DIST_BASE[d] + bits.take(DIST_EXTRA[d])?;
+        if distance > out.len() {
+            return Err("distance too far back".to_string());
+        }
+        if out.len() + len > limit {
+            return Err(over_limit(limit));
+        }
+        // Copied a byte at a time: the match may overlap the bytes it produces.
+        let start = out.len() - distance;
+        for k in 0..len {
+            out.push(out[start + k]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Made with Python's zlib, gzip, tarfile and zipfile modules.
+    const SMALL: &[u8] = b"def main():\n    helper()\n";
+    const DYNAMIC: &[u8] = include_bytes!("../tests/fixtures/archives/dynamic.txt");
+    const LIMIT: usize = 1 << 20;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/archives").join(name)
+    }
+
+    fn paths(entries: &[Entry]) -> Vec<String> {
+        entries.iter().map(|entry| entry.path.to_string_lossy().replace('\\', "/")).collect()
+    }
+
+    #[test]
+    fn inflates_stored_fixed_and_dynamic_blocks() {
+        let block_type = |raw: &[u8]| (raw[0] >> 1) & 3;
+        for (name, btype, expected) in [("stored.deflate", 0, SMALL), ("fixed.deflate", 1, SMALL), ("dynamic.deflate", 2, DYNAMIC)] {
+            let raw = std::fs::read(fixture(name)).unwrap();
+            assert_eq!(block_type(&raw), btype, "{}", name);
+            assert_eq!(inflate(&raw, LIMIT).unwrap(), (expected.to_vec(), raw.len()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn reads_gnu_long_names() {
+        let entries = read_archive(&fixture("gnu.tar"), LIMIT, |_| true).unwrap();
+        let long = format!("src/{}/gnu.py", ["a_rather_long_directory_name"; 4].join("/"));
+        assert_eq!(paths(&entries), ["src/main.py", long.as_str(), "evil.py"]);
+        assert!(entries.iter().all(|entry| entry.data == SMALL));
+    }
+
+    #[test]
+    fn reads_pax_paths_from_a_gzipped_tar() {
+        let entries = read_archive(&fixture("pax.tar.gz"), LIMIT, |_| true).unwrap();
+        let long = format!("src/{}/pax.py", ["a_rather_long_directory_name"; 4].join("/"));
+        assert_eq!(paths(&entries), [long.as_str(), "short.py"]);
+        assert_eq!(entries[0].data, SMALL);
+        assert_eq!(entries[1].data, DYNAMIC);
+    }
+
+    #[test]
+    fn reads_a_commented_zip() {
+        let entries = read_archive(&fixture("commented.zip"), LIMIT, |path| path.extension().is_some_and(|ext| ext == "py")).unwrap();
+        assert_eq!(paths(&entries), ["pkg/stored.py", "pkg/deflated.py", "evil.py"]);
+        assert_eq!(entries[0].data, SMALL);
+        assert_eq!(entries[1].data, DYNAMIC);
+    }
+
+    #[test]
+    fn entry_paths_stay_inside_the_archive() {
+        assert_eq!(entry_path("../../evil.py"), Path::new("evil.py"));
+        assert_eq!(entry_path("/etc/../src/./a.py"), Path::new("etc/src/a.py"));
+        assert_eq!(entry_path("./src/a.py"), Path::new("src/a.py"));
+    }
+
+    #[test]
+    fn decompression_stops_at_the_limit() {
+        // A megabyte of zeros in about a kilobyte.
+        let bomb = std::fs::read(fixture("zeros.deflate")).unwrap();
+        assert_eq!(inflate(&bomb, LIMIT).unwrap().0.len(), LIMIT);
+        assert!(inflate(&bomb, LIMIT - 1).is_err());
+
+        let tar = std::fs::read(fixture("pax.tar.gz")).unwrap();
+        assert!(gunzip(&tar, DYNAMIC.len()).is_err());
+        assert!(matches!(read_archive(&fixture("pax.tar.gz"), 1000, |_| true), Err(ParseError::FileTooLarge(_))));
+    }
+
+    #[test]
+    fn zip_entries_over_the_limit_are_skipped() {
+        let zip = std::fs::read(fixture("commented.zip")).unwrap();
+        let entries = zip_entries(&zip, 1000, &|_: &Path| true).unwrap();
+        assert_eq!(paths(&entries), ["pkg/stored.py", "evil.py"]);
+    }
+
+    #[test]
+    fn corrupt_data_fails_its_crc() {
+        let mut tar = std::fs::read(fixture("pax.tar.gz")).unwrap();
+        let crc = tar.len() - 8;
+        tar[crc] ^= 1;
+        assert!(gunzip(&tar, LIMIT).is_err());
+
+        let mut zip = std::fs::read(fixture("commented.zip")).unwrap();
+        let stored = zip.windows(SMALL.len()).position(|window| window == SMALL).unwrap();
+        zip[stored] ^= 1;
+        assert!(zip_entries(&zip, LIMIT, &|_: &Path| true).is_err());
+    }
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
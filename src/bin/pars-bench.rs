@@ -0,0 +1,125 @@
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+use clap::{Parser, ValueEnum};
+use pars::config::Config;
+use pars::file_info::Language;
+use pars::parser::{parse_source, read_source};
+
+/// Times the parser on generated sources of growing size.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Function counts of the generated files
+    #[clap(long, value_delimiter = ',', default_values_t = [100, 1_000, 5_000])]
+    sizes: Vec<usize>,
+
+    /// Timed parses per size; the best and mean are reported
+    #[clap(long, default_value_t = 5)]
+    iterations: usize,
+
+    /// Calls each generated function makes to functions defined after it
+    #[clap(long, default_value_t = 3)]
+    calls: usize,
+
+    #[clap(long, value_enum, default_value_t = BenchLanguage::Py)]
+    language: BenchLanguage,
+
+    /// Parse with `--edges-only`, skipping signatures and columns
+    #[clap(long)]
+    edges_only: bool,
+
+    /// Also write each source to a temporary file and time reading it back, with and without `--mmap`
+    #[clap(long)]
+    io: bool,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum BenchLanguage {
+    Py,
+    Rs,
+}
+
+/// A file of `count` functions, each calling the next `calls` ones and padded with a few
+/// plain statements, so edges and non-call lines both scale with the size.
+fn generate(language: BenchLanguage, count: usize, calls: usize) -> String {
+    let mut source = String::new();
+    for i in 0..count {
+        let callees = (i + 1..count).take(calls);
+        match language {
+            BenchLanguage::Py => {
+                writeln!(source, "def func_{}(x, y):", i).unwrap();
+                writeln!(source, "    total = x + y  # padding").unwrap();
+                for callee in callees {
+                    writeln!(source, "    total += func_{}(x, total)", callee).unwrap();
+                }
+                writeln!(source, "    return total\n").unwrap();
+            }
+            BenchLanguage::Rs => {
+                writeln!(source, "fn func_{}(x: u64, y: u64) -> u64 {{", i).unwrap();
+                writeln!(source, "    let mut total = x + y; // padding").unwrap();
+                for callee in callees {
+                    writeln!(source, "    total += func_{}(x, total);", callee).unwrap();
+                }
+                writeln!(source, "    total\n}}\n").unwrap();
+            }
+        }
+    }
+    source
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config = Config { max_functions: usize::MAX, edges_only: args.edges_only, ..Config::default() };
+    let language = match args.language {
+        BenchLanguage::Py => Language::Py,
+        BenchLanguage::Rs => Language::Rs,
+    };
+
+    print!("{:>10} {:>10} {:>12} {:>10} {:>10} {:>14}", "functions", "lines", "bytes", "best ms", "mean ms", "functions/s");
+    if args.io {
+        print!(" {:>10} {:>10}", "read ms", "mmap ms");
+    }
+    println!();
+    for &size in &args.sizes {
+        let source = generate(args.language, size, args.calls);
+        let mut times = Vec::with_capacity(args.iterations);
+        for _ in 0..args.iterations.max(1) {
+            let start = Instant::now();
+            let functions = parse_source(&source, &language, &config)?;
+            times.push(start.elapsed());
+            assert_eq!(functions.len(), size, "generated functions were not all found");
+        }
+
+        let best = times.iter().min().copied().unwrap_or_default();
+        let mean = times.iter().sum::<Duration>() / times.len() as u32;
+        print!(
+            "{:>10} {:>10} {:>12} {:>10.2} {:>10.2} {:>14.0}",
+            size,
+            source.lines().count(),
+            source.len(),
+            best.as_secs_f64() * 1000.0,
+            mean.as_secs_f64() * 1000.0,
+            size as f64 / best.as_secs_f64(),
+        );
+        if args.io {
+            let path = std::env::temp_dir().join(format!("pars-bench-{}-{}.src", std::process::id(), size));
+            std::fs::write(&path, &source)?;
+            let read = best_of(args.iterations, || read_source(&path, false).map(|text| text.len()));
+            let mapped = best_of(args.iterations, || read_source(&path, true).map(|text| text.len()));
+            std::fs::remove_file(&path)?;
+            print!(" {:>10.3} {:>10.3}", read?.as_secs_f64() * 1000.0, mapped?.as_secs_f64() * 1000.0);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// The fastest of `iterations` runs of `read`, which returns the byte count it saw.
+fn best_of<E>(iterations: usize, mut read: impl FnMut() -> Result<usize, E>) -> Result<Duration, E> {
+    let mut best = Duration::MAX;
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        std::hint::black_box(read()?);
+        best = best.min(start.elapsed());
+    }
+    Ok(best)
+}
@@ -1,83 +1,593 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use clap::Parser;
-use pars::{FnInfo, find_roots, print_tree};
-use pars::cli::Cli;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use pars::{FnInfo, Location, TreeOptions, Visibility, anonymize, dependency_cycles, detect_roots, estimated_memory, filter_fan, find_cycles, find_orphans, neighborhood, normalize_name, normalize_names, not_reached, print_tree, prune_leaves, pseudonyms, reachable_from, rename_functions};
+use pars::cli::{Cli, InfoLevel, OutputFormat, PathStyle, Report, RootDetection};
 use pars::file_info::FileInfo;
-use pars::config::Config;
-use pars::parser::parse_file;
+use pars::config::{Config, DEFAULT_THREADS};
+use pars::output;
+use pars::parser::{MAIN_BLOCK, MODULE_BLOCK, collect_archive_files, collect_source_files, parse_archive_files, parse_directory, parse_file, parse_labeled};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::parse();
-    let config = Config::from(&args);
-    let path = &args.file_path;
-    let file_info = FileInfo::from_path(&path)?;
+/// Entrypoints for `--root-detection named` when no `--root-name` is given.
+const DEFAULT_ROOT_NAMES: [&str; 3] = ["main", MAIN_BLOCK, MODULE_BLOCK];
 
-    println!("Analyzing file: {}", path.display());
-    println!("cache?={}", config.enable_cache);
+/// Exit code when `--fail-on-cycles` is set and the graph has call cycles.
+const EXIT_CYCLES: u8 = 2;
+/// Exit code when `--fail-on-dead` is set and some functions are unreachable.
+const EXIT_DEAD: u8 = 3;
+/// Exit code when `--fail-on-orphans` is set and some functions are never called.
+const EXIT_ORPHANS: u8 = 4;
+/// Allowlist for `--fail-on-orphans`, read from the analyzed directory (a file's own directory).
+const PARSIGNORE: &str = ".parsignore";
+/// `--threads` beyond this many per CPU (and beyond the default) only adds scheduling overhead.
+const THREADS_PER_CPU: usize = 4;
 
-    if !path.exists() {
-        return Err(format!("File does not exist: {}", path.display()).into());
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args = Cli::parse();
+    pars::log::set_level(args.verbose);
+    warn_on_limits(&args);
+    let mut config = Config::from(&args);
+    if let Some(lang_file) = &args.lang_file {
+        config.custom_langs = pars::lang::load_lang_file(lang_file)?;
     }
-    
-    if !path.is_file() {
-        return Err(format!("Path is not a file: {}", path.display()).into());
+    if args.json_schema {
+        output::write_json_schema(&mut std::io::stdout().lock())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.lsp {
+        pars::lsp::run(&config)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let Some(path) = &args.file_path else {
+        return Err("No file or directory given".into());
+    };
+    let human = args.format == OutputFormat::Tree && !args.compact;
+    if args.output_dir.is_some() && matches!(args.format, OutputFormat::Tree | OutputFormat::TreeJson) {
+        return Err("--output-dir needs a file format: --format csv, json, plantuml or ndjson-edges".into());
+    }
+    if args.report.contains(&Report::Wrappers) && !args.include_external {
+        return Err("--report wrappers needs --include-external to know which calls leave the code".into());
     }
-    
-    let metadata = std::fs::metadata(path)?;
-    let file_size_kb = metadata.len() as f64 / 1024.0;
 
-    if file_size_kb < 1.0 {
-        println!("File size: {} bytes", metadata.len());
-    } else {
-        println!("File size: {:.2} KB", file_size_kb);
+    let stdin = path.as_os_str() == "-";
+    if !stdin && !path.exists() {
+        return Err(format!("File does not exist: {}", path.display()).into());
+    }
+    let archive = !stdin && path.is_file() && pars::archive::is_archive(path);
+    if archive && args.since.is_some() {
+        return Err("--since needs a git checkout, not an archive".into());
+    }
+    if (stdin || archive) && args.imports {
+        return Err("--imports needs a file or directory on disk".into());
     }
 
     let start = std::time::Instant::now();
-    let functions = match parse_file(&file_info, &config) {
+    // Size of the source text read during the parse, for `--report-mem`.
+    let mut content_bytes = 0;
+    // Functions the `--since` diff touches.
+    let mut changed = HashSet::new();
+    // Text `--context-lines` quotes that isn't in a file on disk.
+    let mut sources = HashMap::new();
+    let parsed = if stdin {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        if human {
+            println!("Analyzing standard input as: {}", args.stdin_name.display());
+            print_file_size(source.len());
+        }
+        content_bytes = source.len();
+        if args.context_lines.is_some() {
+            sources.insert(args.stdin_name.clone(), source.lines().map(String::from).collect());
+        }
+        parse_labeled(&source, &args.stdin_name, &config)
+    } else if args.imports {
+        if human {
+            println!("Analyzing imports of: {}", path.display());
+        }
+        pars::imports::parse_imports(path, &config)
+    } else if let Some(git_ref) = &args.since {
+        if human {
+            println!("Analyzing changes since {}: {}", git_ref, path.display());
+        }
+        pars::diff::parse_since(path, git_ref, &config).map(|(functions, touched)| {
+            changed = touched;
+            functions
+        })
+    } else if archive {
+        if human {
+            println!("Analyzing archive: {}", path.display());
+        }
+        let entries = collect_archive_files(path, &config)?;
+        content_bytes = entries.iter().map(|entry| entry.data.len()).sum();
+        if args.context_lines.is_some() {
+            for entry in &entries {
+                sources.insert(entry.path.clone(), String::from_utf8_lossy(&entry.data).lines().map(String::from).collect());
+            }
+        }
+        parse_archive_files(&entries, &config)
+    } else if path.is_dir() {
+        if human {
+            println!("Analyzing directory: {}", path.display());
+            println!("cache?={} threads={}", config.enable_cache, config.threads);
+        }
+        if args.report_mem {
+            for file in collect_source_files(path, &config)? {
+                content_bytes += std::fs::metadata(file)?.len() as usize;
+            }
+        }
+        parse_directory(path, &config)
+    } else {
+        let file_info = FileInfo::from_path(path)?;
+        if human {
+            println!("Analyzing file: {}", path.display());
+            println!("cache?={}", config.enable_cache);
+            print_file_size(file_info.file_size);
+        }
+        content_bytes = file_info.file_size;
+        parse_file(&file_info, &config)
+    };
+
+    let mut functions = match parsed {
         Ok(f) => f,
         Err(e) => {
             eprintln!("Failed to parse file: {}", e);
             return Err(e.into());
         }
     };
-    
-    let parse_duration = start.elapsed();
-    println!("Parsing completed in {:?}", parse_duration);
+
+    if args.since.is_some() {
+        let around = neighborhood(&functions, &changed);
+        functions.retain(|name, _| around.contains(name));
+        for info in functions.values_mut() {
+            info.callees.retain(|(callee, _)| around.contains(callee));
+            info.call_cols.retain(|callee, _| around.contains(callee));
+            info.references.retain(|callee| around.contains(callee));
+        }
+    }
+
+    if args.normalize_names {
+        functions = normalize_names(functions);
+        changed = changed.iter().map(|name| normalize_name(name)).collect();
+    }
+
+    if !args.rename_map.is_empty() {
+        let renames = load_rename_map(&args.rename_map)?;
+        let rename = |name: &str| renames.get(name).cloned().unwrap_or_else(|| name.to_string());
+        functions = rename_functions(functions, rename);
+        changed = changed.iter().map(|name| rename(name)).collect();
+    }
+
+    // Numbered over every function, so what the filters below drop (and report as dead) has one too.
+    let pseudonyms = args.anonymize.then(|| pseudonyms(&functions));
+
+    if args.public_only {
+        functions.retain(|_, info| info.visibility == Visibility::Public);
+        let public: HashSet<String> = functions.keys().cloned().collect();
+        for info in functions.values_mut() {
+            info.callees.retain(|(callee, _)| public.contains(callee));
+            info.call_cols.retain(|callee, _| public.contains(callee));
+            info.references.retain(|callee| public.contains(callee));
+        }
+    }
+
+    if args.no_self_edges {
+        for (name, info) in functions.iter_mut() {
+            info.callees.retain(|(callee, _)| callee != name);
+            info.call_cols.remove(name);
+        }
+    }
+
+    // Before roots and reachability are worked out, which pruning changes.
+    if let Some(levels) = args.prune_leaves {
+        prune_leaves(&mut functions, levels);
+    }
+    if args.min_fan_in.is_some() || args.min_fan_out.is_some() {
+        filter_fan(&mut functions, args.min_fan_in.unwrap_or(0), args.min_fan_out.unwrap_or(0));
+    }
+
+    if args.is_deterministic() {
+        functions = functions.into_iter().map(|(name, info)| (name, info.normalized())).collect();
+    }
+
+    // The query names as the functions spell them, which `--ignore-case` lets differ.
+    let entry = args.entry.as_deref().map(|name| resolve_entry(&functions, name, args.ignore_case)).transpose()?;
+    let root_names: Vec<String> = if args.ignore_case {
+        args.root_name.iter().flat_map(|name| case_matches(&functions, name)).collect()
+    } else {
+        args.root_name.clone()
+    };
+
+    let roots = match &entry {
+        Some(entry) => vec![entry.clone()],
+        None if root_names.is_empty() => detect_roots(&functions, args.root_detection, &DEFAULT_ROOT_NAMES.map(String::from)),
+        None => detect_roots(&functions, args.root_detection, &root_names),
+    };
+    let dead = not_reached(&functions, &reachable_from(&functions, roots.iter().cloned()));
+    let orphans = if args.fail_on_orphans {
+        let entrypoints: Vec<String> =
+            DEFAULT_ROOT_NAMES.iter().map(|name| name.to_string()).chain(root_names).chain(entry.clone()).collect();
+        let allowed = load_parsignore(path)?;
+        // A library's public functions are called from outside, so they're entrypoints too.
+        let api = args.root_detection == RootDetection::Public;
+        find_orphans(&functions, &entrypoints, |name| {
+            allowed.is_match(name) || (api && functions[name].visibility == Visibility::Public)
+        })
+    } else {
+        Vec::new()
+    };
+    // Outside the tree (which lists them separately), an entrypoint narrows the graph to what it reaches.
+    if entry.is_some() && (args.tui || !human) {
+        functions.retain(|name, _| dead.binary_search(name).is_err());
+    }
+
+    // Last, once every lookup by name is done. Pseudonyms sort like the names, so `dead` stays sorted.
+    let (roots, dead, orphans, changed) = if let Some(pseudonyms) = &pseudonyms {
+        if let Some(map_path) = &args.anonymize_map {
+            let mapping: String = pseudonyms.iter().map(|(name, pseudonym)| format!("{}={}\n", name, pseudonym)).collect();
+            std::fs::write(map_path, mapping).map_err(|e| format!("--anonymize-map {}: {}", map_path.display(), e))?;
+        }
+        let rename = |name: String| pseudonyms.get(&name).cloned().unwrap_or(name);
+        let rename_all = |names: Vec<String>| -> Vec<String> { names.into_iter().map(rename).collect() };
+        functions = anonymize(functions, pseudonyms);
+        let changed = changed.into_iter().map(rename).collect();
+        (rename_all(roots), rename_all(dead), rename_all(orphans), changed)
+    } else {
+        (roots, dead, orphans, changed)
+    };
+
+    // A bare line number is ambiguous once several files are merged.
+    // Functions in an archive already carry their path inside it.
+    let location = match args.path_style {
+        _ if archive => Location::RelativeTo(PathBuf::new()),
+        Some(PathStyle::Absolute) => Location::Absolute,
+        Some(PathStyle::Relative) if !path.is_dir() => Location::RelativeTo(path.parent().unwrap_or(path).to_path_buf()),
+        Some(PathStyle::Relative) => Location::RelativeTo(path.clone()),
+        None if path.is_dir() => Location::RelativeTo(path.clone()),
+        None => Location::Line,
+    };
+
+    let memory = args.report_mem.then(|| content_bytes + estimated_memory(&functions));
+
+    if args.tui {
+        pars::tui::run(&pars::graph::CallGraph::new(&functions), &location)?;
+        return Ok(exit_code(&args, &functions, &dead, &orphans));
+    }
+
+    if let Some(dir) = &args.output_dir {
+        let written = write_per_file(&functions, args.format, args.json_pretty, if archive { Path::new("") } else { path }, dir)?;
+        println!("Wrote {} report files under {}", written, dir.display());
+        return Ok(exit_code(&args, &functions, &dead, &orphans));
+    }
+
+    match args.format {
+        _ if args.compact => output::write_compact(&mut std::io::stdout().lock(), &functions)?,
+        OutputFormat::Tree => {
+            if !args.is_deterministic() {
+                println!("Parsing completed in {:?}", start.elapsed());
+            }
+            if args.context_lines.is_some() {
+                load_sources(&functions, &mut sources);
+            }
+            print_hierarchy(&args, &functions, &roots, &dead, &changed, &location, sources)?;
+        }
+        OutputFormat::TreeJson => output::write_tree_json(&mut std::io::stdout().lock(), &functions, &roots, &dead, args.json_pretty)?,
+        format => write_file_format(&mut std::io::stdout().lock(), format, &functions, args.json_pretty)?,
+    }
+
+    // Machine formats own stdout, so the estimate goes to stderr there.
+    if let Some(bytes) = memory {
+        let report = format!("Approximate peak memory: {:.2} KB", bytes as f64 / 1024.0);
+        if human {
+            println!("\n{}", report);
+        } else {
+            eprintln!("{}", report);
+        }
+    }
+
+    Ok(exit_code(&args, &functions, &dead, &orphans))
+}
+
+/// Writes `functions` in one of the machine-readable `--format`s.
+fn write_file_format(out: &mut impl Write, format: OutputFormat, functions: &HashMap<String, FnInfo>, pretty: bool) -> std::io::Result<()> {
+    match format {
+        OutputFormat::Csv => output::write_csv(out, functions),
+        OutputFormat::Json => output::write_json(out, functions, pretty),
+        OutputFormat::Plantuml => output::write_plantuml(out, functions),
+        OutputFormat::NdjsonEdges => output::write_ndjson_edges(out, functions),
+        OutputFormat::Tree | OutputFormat::TreeJson => unreachable!("trees are written from the roots, not per file"),
+    }
+}
+
+/// Writes each source file's functions, with all their calls, as a `format` report at
+/// `dir/<path relative to root>.<extension>`; returns how many files were written.
+fn write_per_file(
+    functions: &HashMap<String, FnInfo>,
+    format: OutputFormat,
+    pretty: bool,
+    root: &Path,
+    dir: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let extension = match format {
+        OutputFormat::Csv => "csv",
+        OutputFormat::Json => "json",
+        OutputFormat::Plantuml => "puml",
+        OutputFormat::NdjsonEdges => "ndjson",
+        OutputFormat::Tree | OutputFormat::TreeJson => unreachable!("--output-dir rejects the tree formats"),
+    };
+
+    let mut files: BTreeMap<&PathBuf, HashMap<String, FnInfo>> = BTreeMap::new();
+    for (name, info) in functions {
+        if let Some(file) = &info.source_file {
+            files.entry(file).or_default().insert(name.clone(), info.clone());
+        }
+    }
+
+    for (file, file_functions) in &files {
+        // A single analyzed file is relative to itself, so it keeps just its name.
+        let relative = file
+            .strip_prefix(root)
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or_else(|| file.file_name().map(Path::new))
+            .unwrap_or(file);
+        let mut target = dir.join(relative).into_os_string();
+        target.push(".");
+        target.push(extension);
+        let target = PathBuf::from(target);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&target)?);
+        write_file_format(&mut out, format, file_functions, pretty)?;
+        out.flush()?;
+    }
+    Ok(files.len())
+}
+
+fn print_file_size(bytes: usize) {
+    let file_size_kb = bytes as f64 / 1024.0;
+    if file_size_kb < 1.0 {
+        println!("File size: {} bytes", bytes);
+    } else {
+        println!("File size: {:.2} KB", file_size_kb);
+    }
+}
+
+fn print_hierarchy(
+    args: &Cli,
+    functions: &HashMap<String, FnInfo>,
+    roots: &[String],
+    dead: &[String],
+    changed: &HashSet<String>,
+    location: &Location,
+    sources: HashMap<PathBuf, Vec<String>>,
+) -> std::io::Result<()> {
     println!("Found {} functions", functions.len());
+    if args.since.is_some() {
+        let mut names: Vec<&String> = changed.iter().collect();
+        names.sort();
+        println!("Changed functions: {}", names.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", "));
+    }
 
     if functions.is_empty() {
         println!("No functions found in the file.");
         return Ok(());
     }
 
+    if args.flatten {
+        println!();
+        output::write_flat(&mut std::io::stdout().lock(), functions, dead, location)?;
+        return write_rankings(args, functions, location);
+    }
+
+    if args.group_by_file
+        && let Some(root) = &args.file_path
+    {
+        println!();
+        output::write_by_file(&mut std::io::stdout().lock(), functions, root)?;
+        return write_rankings(args, functions, location);
+    }
+
     println!("\nFunction Call Hierarchy:\n{}", "=".repeat(40));
 
-    let roots = find_roots(&functions);
     let mut visited = HashSet::new();
+    let tree_options = TreeOptions {
+        show_signatures: matches!(args.info_level, InfoLevel::L3),
+        show_kinds: !matches!(args.info_level, InfoLevel::L1),
+        highlight: changed.clone(),
+        location: location.clone(),
+        callee_files: args.callee_files,
+        context_lines: args.context_lines,
+        sources,
+    };
 
     if roots.is_empty() {
-        println!("No root functions found (all functions are called by others or part of cycles)");
+        match args.root_detection {
+            RootDetection::Named => println!("No root functions found (none of the named entrypoints is defined)"),
+            RootDetection::Public => println!("No root functions found (no public functions or named entrypoints)"),
+            _ => println!("No root functions found (all functions are called by others or part of cycles)"),
+        }
     } else {
         for (i, root) in roots.iter().enumerate() {
-            let is_last = i == roots.len() - 1;
-            print_tree(root, &functions, "".to_string(), is_last, &mut visited);
+            if args.separate_roots {
+                // Each root gets its own tree, so functions shared between roots show under every one.
+                println!("\n=== root: {} ===", root);
+                visited.clear();
+                print_tree(root, functions, "".to_string(), true, &mut visited, &tree_options);
+            } else {
+                let is_last = i == roots.len() - 1;
+                print_tree(root, functions, "".to_string(), is_last, &mut visited, &tree_options);
+            }
         }
     }
 
-    let mut remaining: Vec<_> = functions
-        .keys()
-        .filter(|k| !visited.contains(*k))
-        .cloned()
+    // An entrypoint is the only root, and already spelled as the output spells names.
+    let entry = args.entry.as_ref().and(roots.first());
+
+    // Cycles nothing outside them calls would otherwise scatter into the orphan list.
+    let dead_cycles: Vec<Vec<String>> = find_cycles(functions)
+        .into_iter()
+        .filter(|cycle| cycle.iter().all(|name| dead.binary_search(name).is_ok()))
         .collect();
+    if !dead_cycles.is_empty() {
+        match entry {
+            Some(entry) => println!("\nCycles not reachable from {}:", entry),
+            None => println!("\nCycles (not reachable from any root):"),
+        }
+        for cycle in &dead_cycles {
+            let members: Vec<String> = cycle
+                .iter()
+                .map(|name| format!("{} ({})", name, location.of(&functions[name])))
+                .collect();
+            println!("  {}", members.join(", "));
+        }
+    }
+
+    let orphans: Vec<&String> = dead.iter().filter(|name| !dead_cycles.iter().any(|cycle| cycle.contains(name))).collect();
+    if !orphans.is_empty() {
+        match entry {
+            Some(entry) => println!("\nNot reachable from {}:", entry),
+            None if args.root_detection == RootDetection::Public => println!("\nInternal functions the public API never reaches:"),
+            None => println!("\nUnreachable / Orphan Functions:"),
+        }
+        for func_name in orphans {
+            println!("  {} ({})", func_name, location.of(&functions[func_name]));
+        }
+    }
 
-    if !remaining.is_empty() {
-        println!("\nUnreachable / Orphan Functions:");
-        remaining.sort();
-        for func_name in remaining {
-            let line_num = functions[&func_name].line_at_call + 1;
-            println!("  {} (line {})", func_name, line_num);
+    write_rankings(args, functions, location)
+}
+
+fn write_rankings(args: &Cli, functions: &HashMap<String, FnInfo>, location: &Location) -> std::io::Result<()> {
+    if let Some(n) = args.top_callers {
+        output::write_top_callers(&mut std::io::stdout().lock(), functions, n, location)?;
+    }
+    if let Some(n) = args.top_callees {
+        output::write_top_callees(&mut std::io::stdout().lock(), functions, n, location)?;
+    }
+    for report in &args.report {
+        match report {
+            Report::Coupling => output::write_coupling(&mut std::io::stdout().lock(), functions, location)?,
+            Report::Depth => output::write_depths(&mut std::io::stdout().lock(), functions, location)?,
+            Report::Wrappers => output::write_wrappers(&mut std::io::stdout().lock(), functions, location)?,
         }
     }
 
     Ok(())
 }
+
+/// Reads the lines of every defining file not already in `sources`; unreadable files are left
+/// out, so their calls show no context.
+fn load_sources(functions: &HashMap<String, FnInfo>, sources: &mut HashMap<PathBuf, Vec<String>>) {
+    for file in functions.values().filter_map(|info| info.source_file.as_ref()) {
+        if !sources.contains_key(file)
+            && let Ok(content) = std::fs::read_to_string(file)
+        {
+            sources.insert(file.clone(), content.lines().map(String::from).collect());
+        }
+    }
+}
+
+/// Warns about a `--threads` value that works but can't be what was meant; zero is refused while
+/// parsing the arguments.
+fn warn_on_limits(args: &Cli) {
+    let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    if args.threads > (cpus * THREADS_PER_CPU).max(DEFAULT_THREADS) {
+        eprintln!("Warning: --threads {} is far more than the {} CPUs available", args.threads, cpus);
+    }
+}
+
+/// The function `--entry` names: that exact key, or with `--ignore-case` the one key equal to it
+/// ignoring case.
+fn resolve_entry(functions: &HashMap<String, FnInfo>, name: &str, ignore_case: bool) -> Result<String, Box<dyn std::error::Error>> {
+    if functions.contains_key(name) {
+        return Ok(name.to_string());
+    }
+    let matches = if ignore_case { case_matches(functions, name) } else { Vec::new() };
+    match matches.as_slice() {
+        [] => Err(format!("Entry function not found: {}", name).into()),
+        [key] => Ok(key.clone()),
+        _ => Err(format!("Entry function {} matches several functions ignoring case: {}", name, matches.join(", ")).into()),
+    }
+}
+
+/// Every function key equal to `name` ignoring case, sorted.
+fn case_matches(functions: &HashMap<String, FnInfo>, name: &str) -> Vec<String> {
+    let name = name.to_lowercase();
+    let mut matches: Vec<String> = functions.keys().filter(|key| key.to_lowercase() == name).cloned().collect();
+    matches.sort();
+    matches
+}
+
+/// Collects `--rename-map` values: each is an `old=new` pair, or the path of a file with one such
+/// pair per line (`#` starts a comment).
+fn load_rename_map(values: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let mut renames = HashMap::new();
+    for value in values {
+        if let Some((old, new)) = value.split_once('=') {
+            renames.insert(old.trim().to_string(), new.trim().to_string());
+            continue;
+        }
+        let content = std::fs::read_to_string(value).map_err(|e| format!("--rename-map {}: {}", value, e))?;
+        for (n, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (old, new) = line
+                .split_once('=')
+                .ok_or_else(|| format!("{}:{}: expected `old=new`, got `{}`", value, n + 1, line))?;
+            renames.insert(old.trim().to_string(), new.trim().to_string());
+        }
+    }
+    Ok(renames)
+}
+
+/// Reads the `--fail-on-orphans` allowlist from the analyzed directory (a file's own directory):
+/// one function name or glob (`Api::*`) per line, `#` starting a comment. No file allows nothing.
+fn load_parsignore(path: &Path) -> Result<GlobSet, Box<dyn std::error::Error>> {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(Path::new(".")) };
+    let mut builder = GlobSetBuilder::new();
+    match std::fs::read_to_string(dir.join(PARSIGNORE)) {
+        Ok(content) => {
+            for line in content.lines() {
+                let pattern = line.split('#').next().unwrap_or_default().trim();
+                if !pattern.is_empty() {
+                    builder.add(Glob::new(pattern).map_err(|e| format!("{}: {}: {}", PARSIGNORE, pattern, e))?);
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(builder.build()?)
+}
+
+/// Applies the `--fail-on-*` gates; cycles take precedence over dead functions, and those over
+/// orphans.
+fn exit_code(args: &Cli, functions: &HashMap<String, FnInfo>, dead: &[String], orphans: &[String]) -> ExitCode {
+    if args.fail_on_cycles {
+        let cycles = dependency_cycles(functions);
+        if !cycles.is_empty() {
+            for cycle in &cycles {
+                eprintln!("Cycle: {}", cycle.join(", "));
+            }
+            return ExitCode::from(EXIT_CYCLES);
+        }
+    }
+
+    if args.fail_on_dead && !dead.is_empty() {
+        eprintln!("Dead functions: {}", dead.join(", "));
+        return ExitCode::from(EXIT_DEAD);
+    }
+
+    if !orphans.is_empty() {
+        eprintln!("Orphan functions: {}", orphans.join(", "));
+        return ExitCode::from(EXIT_ORPHANS);
+    }
+
+    ExitCode::SUCCESS
+}
@@ -1,13 +1,24 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use crate::error::ParseError;
 use crate::FnInfo;
 
+/// Bumped whenever the cached `FnInfo` shape changes, so older entries are re-parsed.
+const CACHE_VERSION: u32 = 8;
+
 #[derive(Serialize, Deserialize, Debug)]
 struct CacheEntry {
+    #[serde(default)]
+    version: u32,
     file_hash: u64,
     last_modified: u64,
+    /// `Config::cache_key` of the run that produced this entry.
+    #[serde(default)]
+    options_hash: u64,
+    /// Seconds since the epoch when the entry was written, checked against `--cache-ttl`.
+    #[serde(default)]
+    cached_at: u64,
     functions: HashMap<String, FnInfo>,
 }
 
@@ -20,13 +31,50 @@ pub fn hash_string(s: &str) -> u64 {
     hasher.finish()
 }
 
-fn get_cache_path(source_path: &PathBuf) -> PathBuf {
-    let mut cache_path = source_path.clone();
+/// [`hash_string`] of the text of the file at `path`, read a buffer at a time rather than whole.
+pub fn hash_file(path: &Path) -> Result<u64, ParseError> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+    use std::io::BufRead;
+
+    let mut hasher = DefaultHasher::new();
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        hasher.write(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    // What `str`'s `Hash` writes after the bytes.
+    hasher.write_u8(0xff);
+    Ok(hasher.finish())
+}
+
+fn get_cache_path(source_path: &Path) -> PathBuf {
+    let mut cache_path = source_path.to_path_buf();
     cache_path.set_extension("funcparse_cache");
     cache_path
 }
 
-pub fn load_cache(source_path: &PathBuf, content: &str) -> Result<Option<HashMap<String, FnInfo>>, ParseError> {
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Loads the cached functions for `source_path` if the entry still matches its content (hashed
+/// as `file_hash`) and options.
+///
+/// With `ttl_secs`, entries written longer ago than that are treated as stale too.
+pub fn load_cache(
+    source_path: &Path,
+    file_hash: u64,
+    options_hash: u64,
+    ttl_secs: Option<u64>,
+) -> Result<Option<HashMap<String, FnInfo>>, ParseError> {
     let cache_path = get_cache_path(source_path);
     
     if !cache_path.exists() {
@@ -39,7 +87,6 @@ pub fn load_cache(source_path: &PathBuf, content: &str) -> Result<Option<HashMap
     let cache_entry: CacheEntry = serde_json::from_str(&cache_content)
         .map_err(|e| ParseError::CacheError(format!("Failed to parse cache: {}", e)))?;
     
-    let current_hash = hash_string(content);
     let metadata = std::fs::metadata(source_path)?;
     let current_modified = metadata.modified()
         .map_err(|e| ParseError::CacheError(format!("Failed to get file modified time: {}", e)))?
@@ -47,19 +94,30 @@ pub fn load_cache(source_path: &PathBuf, content: &str) -> Result<Option<HashMap
         .map_err(|e| ParseError::CacheError(format!("Invalid modified time: {}", e)))?
         .as_secs();
     
-    if cache_entry.file_hash == current_hash && cache_entry.last_modified == current_modified {
-        println!("Using cached parse results");
+    if cache_entry.version == CACHE_VERSION
+        && cache_entry.file_hash == file_hash
+        && cache_entry.last_modified == current_modified
+        && cache_entry.options_hash == options_hash
+        && ttl_secs.is_none_or(|ttl| now_secs().saturating_sub(cache_entry.cached_at) <= ttl)
+    {
+        crate::info!("Using cached parse results for {}", source_path.display());
         Ok(Some(cache_entry.functions))
     } else {
-        println!("Cache is stale, will re-parse");
+        crate::info!("Cache is stale for {}, will re-parse", source_path.display());
         Ok(None)
     }
 }
 
-pub fn save_cache(source_path: &PathBuf, content: &str, functions: &HashMap<String, FnInfo>) -> Result<(), ParseError> {
+/// Writes the cache entry for `source_path`, whose content hashes to `file_hash`, indented when `pretty` (`--json-pretty`) and compact otherwise.
+pub fn save_cache(
+    source_path: &Path,
+    file_hash: u64,
+    options_hash: u64,
+    functions: &HashMap<String, FnInfo>,
+    pretty: bool,
+) -> Result<(), ParseError> {
     let cache_path = get_cache_path(source_path);
     
-    let file_hash = hash_string(content);
     let metadata = std::fs::metadata(source_path)?;
     let last_modified = metadata.modified()
         .map_err(|e| ParseError::CacheError(format!("Failed to get file modified time: {}", e)))?
@@ -68,17 +126,21 @@ pub fn save_cache(source_path: &PathBuf, content: &str, functions: &HashMap<Stri
         .as_secs();
     
     let cache_entry = CacheEntry {
+        version: CACHE_VERSION,
         file_hash,
         last_modified,
+        options_hash,
+        cached_at: now_secs(),
         functions: functions.clone(),
     };
     
-    let cache_json = serde_json::to_string_pretty(&cache_entry)
+    let serialized = if pretty { serde_json::to_string_pretty(&cache_entry) } else { serde_json::to_string(&cache_entry) };
+    let cache_json = serialized
         .map_err(|e| ParseError::CacheError(format!("Failed to serialize cache: {}", e)))?;
     
     std::fs::write(&cache_path, cache_json)
         .map_err(|e| ParseError::CacheError(format!("Failed to write cache: {}", e)))?;
     
-    println!("Cached parse results to: {}", cache_path.display());
+    crate::info!("Cached parse results to: {}", cache_path.display());
     Ok(())
 }
@@ -1,5 +1,15 @@
 use std::path::PathBuf;
 use clap::{Parser, ValueEnum};
+use crate::config::{DEFAULT_MAX_FILE_SIZE_MB, DEFAULT_MAX_FUNCTIONS, DEFAULT_MAX_LINE_LENGTH, DEFAULT_THREADS};
+
+/// Parses a count that must be positive: no workers, or zero-sized blocks, would never finish.
+fn at_least_one(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(count) => Ok(count),
+        Err(e) => Err(e.to_string()),
+    }
+}
 
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
@@ -9,26 +19,341 @@ pub enum InfoLevel {
     L3,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Indented call hierarchy with a summary
+    Tree,
+    /// `caller,callee,call_line` rows with a header
+    Csv,
+    /// Functions sorted by name with their callees; implies `--deterministic`
+    Json,
+    /// A PlantUML component diagram (`@startuml` .. `@enduml`)
+    Plantuml,
+    /// One JSON object per edge and line: `{"from", "to", "line", "from_file", "to_file"}`
+    NdjsonEdges,
+    /// The tree as nested `{"name", "line", "children"}` nodes, repeats marked `"ref": true`;
+    /// implies `--deterministic`
+    TreeJson,
+}
+
+/// How file paths are shown next to line numbers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PathStyle {
+    /// Relative to the analyzed directory
+    Relative,
+    /// Absolute
+    Absolute,
+}
+
+/// Which functions the tree starts from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RootDetection {
+    /// Functions nothing else calls
+    Uncalled,
+    /// The functions named by `--root-name` (default `main` and `__main__`)
+    Named,
+    /// Uncalled functions first, then any function not yet reached, so cycles get a tree too
+    All,
+    /// A library's API: the `--root-name` entrypoints and every public function, so internal
+    /// functions the API never reaches are reported as unreachable
+    Public,
+}
+
+/// Analyses `--report` prints after the human-readable output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Report {
+    /// Graph density, average call-path length, functions in cycles and articulation functions
+    Coupling,
+    /// Functions by the depth of the call tree below them, deepest first
+    Depth,
+    /// Functions that only call external code, such as adapters and facades (needs `--include-external`)
+    Wrappers,
+}
+
+/// How directory mode resolves a function name defined in more than one file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MergeStrategy {
+    /// Key each colliding definition as `<relative/path>::<name>`
+    Qualify,
+    /// Keep the definition from the first file in path order
+    First,
+    /// Fail with an error naming both files
+    Error,
+}
+
 #[derive(Parser, Debug)]
 pub struct Cli {
-    pub file_path: PathBuf,
+    /// Source file, a directory to parse every supported file in, or `-` to read standard input
+    #[clap(required_unless_present_any = ["json_schema", "lsp"])]
+    pub file_path: Option<PathBuf>,
+
+    /// Filename standing in for standard input (`-`), shown in output and used to pick the language
+    #[clap(long, value_name = "LABEL", default_value = "<stdin>")]
+    pub stdin_name: PathBuf,
 
     #[clap(value_enum, default_value_t = InfoLevel::L1)]
     pub info_level: InfoLevel,
     
-    /// Number of threads for parallel processing if enabled 
-    #[clap(long, default_value_t = 8)]
+    /// Number of threads for parallel processing (used when parsing a directory)
+    #[clap(long, default_value_t = DEFAULT_THREADS, value_parser = at_least_one)]
     pub threads: usize,
     
-    /// Block size in KB for parallel reading if enabled
-    #[clap(long, default_value_t = 16)]
-    pub block_size_kb: usize,
-    
     #[clap(long)]
     pub no_cache: bool,
+
+    /// Refuse files larger than this many megabytes (skipped with a warning in directory mode)
+    #[clap(long = "max-file-size", value_name = "MB", default_value_t = DEFAULT_MAX_FILE_SIZE_MB)]
+    pub max_file_size_mb: usize,
+
+    /// Refuse a file that defines more than this many functions (usually a misdetected language)
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_FUNCTIONS)]
+    pub max_functions: usize,
+
+    /// Refuse a file with a line longer than this many characters (usually minified or generated)
+    #[clap(long, value_name = "CHARS", default_value_t = DEFAULT_MAX_LINE_LENGTH, value_parser = at_least_one)]
+    pub max_line_length: usize,
+
+    /// Treat cache entries older than this many seconds as stale (default: never expire)
+    #[clap(long, value_name = "SECONDS")]
+    pub cache_ttl: Option<u64>,
     
+    /// Memory-map source files instead of copying them into memory (for very large inputs);
+    /// invalid UTF-8 is replaced rather than refused
+    #[clap(long)]
+    pub mmap: bool,
+
+    /// Override the keyword that introduces a function (e.g. `proc` for a custom DSL)
+    #[clap(long)]
+    pub def_keyword: Option<String>,
+
+    /// Override the token that opens a function body (e.g. `{` or `:`)
+    #[clap(long)]
+    pub body_open: Option<String>,
+
+    /// TOML file of extra `[language.<name>]` definitions, matched by file extension
+    #[clap(long)]
+    pub lang_file: Option<PathBuf>,
+
+    /// Print the N functions with the most callers (highest fan-in)
+    #[clap(long, value_name = "N")]
+    pub top_callers: Option<usize>,
+
+    /// Print the N functions that call the most others (highest fan-out)
+    #[clap(long, value_name = "N")]
+    pub top_callees: Option<usize>,
+
+    /// Reports to print after the hierarchy (comma-separated or repeated)
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub report: Vec<Report>,
+
+    /// Exit with status 2 if the call graph contains cycles
+    #[clap(long)]
+    pub fail_on_cycles: bool,
+
+    /// Exit with status 3 if any function is unreachable from the roots
+    #[clap(long)]
+    pub fail_on_dead: bool,
+
+    /// Exit with status 4 if some function is never called and is not an entrypoint
+    /// (`main`, `--root-name`, `--entry`); names or globs in `.parsignore` are allowed
+    #[clap(long)]
+    pub fail_on_orphans: bool,
+
+    /// Record Rust macro invocations (`name!(..)`, `name![..]`, `name!{..}`) as edges
+    #[clap(long)]
+    pub include_macros: bool,
+
+    /// Drop test functions (`#[test]`, `#[cfg(test)]` modules, Python `test_*` and `Test*` classes)
+    #[clap(long, overrides_with = "include_tests")]
+    pub exclude_tests: bool,
+
+    /// Keep test functions in the graph (the default; overrides an earlier `--exclude-tests`)
+    #[clap(long, overrides_with = "exclude_tests")]
+    pub include_tests: bool,
+
+    /// Don't match `name(` to a function once the caller has bound a local `name` (heuristic)
+    #[clap(long)]
+    pub definition_only_edges: bool,
+
+    /// Drop calls made outside every function instead of graphing them from a `<module>` node
+    #[clap(long)]
+    pub no_module_node: bool,
+
+    /// Drop recursive self-calls (`foo` calling `foo`) from every output
+    #[clap(long)]
+    pub no_self_edges: bool,
+
+    /// Show calls through imported modules (`utils.helper()`) as edges to `[external]` nodes
+    #[clap(long)]
+    pub include_external: bool,
+
+    /// With `--include-external`, also show calls into the standard library (`os.path.join`)
+    #[clap(long)]
+    pub show_stdlib: bool,
+
+    /// Comma-separated modules/builtins to leave out of `--include-external`, replacing the language's list
+    #[clap(long, value_delimiter = ',')]
+    pub builtins: Option<Vec<String>>,
+
+    /// Also record functions mentioned by name without being called (`register(callback)`) as reference edges
     #[clap(long)]
-    pub parallel_read: bool,
+    pub include_references: bool,
+
+    /// Only find the call edges: skip signatures and name/call columns, for speed on huge trees
+    #[clap(long, conflicts_with = "lsp")]
+    pub edges_only: bool,
+
+    /// Strip generic arguments, lifetimes and trait qualifiers from names (`<Foo<T> as Bar>::f` -> `Foo::f`),
+    /// merging functions that then share a name; JSON keeps the originals as `raw_names`
+    #[clap(long)]
+    pub normalize_names: bool,
+
+    /// Rename a function before rendering, `OLD=NEW` (repeatable), so diffing two `--compact` runs
+    /// across a refactor ignores known renames; a value without `=` names a file of such lines
+    #[clap(long, value_name = "OLD=NEW|FILE")]
+    pub rename_map: Vec<String>,
+
+    /// Replace function names with pseudonyms (`fn_001`, `fn_002`, ..) numbered in name order and
+    /// drop signatures, to share a graph's shape without its names
+    #[clap(long, conflicts_with_all = ["context_lines", "verbose"])]
+    pub anonymize: bool,
+
+    /// With `--anonymize`, also write the `NAME=PSEUDONYM` mapping to this file, one per line
+    #[clap(long, value_name = "PATH", requires = "anonymize")]
+    pub anonymize_map: Option<PathBuf>,
+
+    /// Keep only public functions (`pub`, no leading underscore, ..) and the calls between them
+    #[clap(long)]
+    pub public_only: bool,
+
+    /// Byte-stable output for snapshot tests: sorted callees and no timing lines
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// How to handle a function name defined in several files (directory mode)
+    #[clap(long, value_enum, default_value_t = MergeStrategy::Qualify)]
+    pub merge_strategy: MergeStrategy,
+
+    /// Directory mode: only parse files matching this glob (repeatable, e.g. `**/*.py`)
+    #[clap(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Directory mode: skip files matching this glob (repeatable); wins over `--include`
+    #[clap(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only show what this function reaches; everything else is reported as not reachable
+    #[clap(long, value_name = "FN")]
+    pub entry: Option<String>,
+
+    /// How to pick the roots the tree starts from
+    #[clap(long, value_enum, default_value_t = RootDetection::Uncalled)]
+    pub root_detection: RootDetection,
+
+    /// Entrypoint for `--root-detection named` (repeatable; default `main` and `__main__`)
+    #[clap(long, value_name = "FN")]
+    pub root_name: Vec<String>,
+
+    /// Match the names given to `--entry` and `--root-name` ignoring case
+    #[clap(long)]
+    pub ignore_case: bool,
+
+    /// With `--ignore-case`, also detect calls ignoring ASCII case (`HELPER()` calls `helper`);
+    /// off by default since it can invent edges in case-sensitive languages
+    #[clap(long, requires = "ignore_case")]
+    pub ignore_case_calls: bool,
+
+    /// Only parse files changed since this git ref and show the functions the diff touches,
+    /// with their direct callers and callees
+    #[clap(long, value_name = "REF")]
+    pub since: Option<String>,
+
+    /// Graph which modules each file imports (`import`, `use`, `#include`) instead of which
+    /// functions call which
+    #[clap(long, conflicts_with = "since")]
+    pub imports: bool,
+
+    /// Browse the call tree interactively instead of printing it
+    #[clap(long)]
+    pub tui: bool,
+
+    /// Print each root's tree under its own `=== root: name ===` header, repeating shared functions
+    #[clap(long)]
+    pub separate_roots: bool,
+
+    /// Print one `name (line N) -> [callees]` line per function, sorted by name, instead of a tree
+    #[clap(long)]
+    pub flatten: bool,
+
+    /// Drop functions that call nothing before rendering; `=N` repeats that N times, peeling
+    /// off the helpers only leaves called
+    #[clap(long, value_name = "N", num_args = 0..=1, default_missing_value = "1", require_equals = true)]
+    pub prune_leaves: Option<usize>,
+
+    /// Drop functions called from fewer than N other functions before rendering (`2` hides
+    /// single-use helpers, but also entrypoints nothing calls)
+    #[clap(long, value_name = "N")]
+    pub min_fan_in: Option<usize>,
+
+    /// Drop functions calling fewer than N other functions before rendering
+    #[clap(long, value_name = "N")]
+    pub min_fan_out: Option<usize>,
+
+    /// Write one `--format` report per analyzed file under DIR, mirroring the source tree
+    /// (`DIR/src/lib.rs.json`) instead of one merged graph on stdout
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["compact", "tui", "flatten", "group_by_file"])]
+    pub output_dir: Option<PathBuf>,
+
+    /// Print only `caller -> callee` lines, one per edge and sorted, for diffing two runs
+    #[clap(long, conflicts_with_all = ["format", "tui", "flatten", "group_by_file"])]
+    pub compact: bool,
+
+    /// Show definitions as `path:line` with this path style (default: relative in directory mode,
+    /// bare line numbers for a single file)
+    #[clap(long, value_enum)]
+    pub path_style: Option<PathStyle>,
+
+    /// In the tree, quote N lines of source before and after each call, under the callee
+    #[clap(long, value_name = "N")]
+    pub context_lines: Option<usize>,
+
+    /// In the tree, show a callee's file only when it differs from its caller's
+    #[clap(long)]
+    pub callee_files: bool,
+
+    /// List functions under the file that defines them, with the calls each makes into other files
+    #[clap(long)]
+    pub group_by_file: bool,
+
+    /// Print an approximate peak memory figure for the parse (source text plus the function map)
+    #[clap(long)]
+    pub report_mem: bool,
+
+    /// Indent `--format json` output (and cache files) for reading; compact by default
+    #[clap(long)]
+    pub json_pretty: bool,
+
+    /// Print the JSON Schema of `--format json` output and exit
+    #[clap(long)]
+    pub json_schema: bool,
+
+    /// Serve call hierarchies to an editor over LSP JSON-RPC on stdin/stdout
+    #[clap(long)]
+    pub lsp: bool,
+
+    /// Diagnostics on stderr: `-v` for cache decisions and skipped lines, `-vv` for every parse decision
+    #[clap(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Output format for the call graph
+    #[clap(long, value_enum, default_value_t = OutputFormat::Tree)]
+    pub format: OutputFormat,
 }
 
 
+
+impl Cli {
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic || matches!(self.format, OutputFormat::Json | OutputFormat::TreeJson)
+    }
+}
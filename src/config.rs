@@ -1,14 +1,143 @@
-use crate::cli::Cli;
+use crate::cli::{Cli, MergeStrategy};
+use crate::lang::LangConfig;
 
 #[derive(Debug)]
 pub struct Config {
     pub enable_cache: bool,
+    /// Cache entries older than this many seconds are re-parsed; `None` never expires them.
+    pub cache_ttl: Option<u64>,
+    /// Files larger than this many bytes are refused instead of read into memory.
+    pub max_file_size: usize,
+    /// A file defining more functions than this is rejected as a likely misdetected language.
+    pub max_functions: usize,
+    /// Longest line, in characters, a file may have before it's refused as minified or generated.
+    pub max_line_length: usize,
+    /// Indent the JSON written to cache files (and, in the CLI, `--format json` output).
+    pub json_pretty: bool,
+    /// Memory-map source files instead of reading them into a `String`.
+    pub mmap: bool,
+    /// Worker threads for directory parsing.
+    pub threads: usize,
+    pub merge_strategy: MergeStrategy,
+    pub def_keyword: Option<String>,
+    pub body_open: Option<String>,
+    /// Languages loaded from `--lang-file`, consulted before the built-in ones.
+    pub custom_langs: Vec<LangConfig>,
+    /// Record Rust macro invocations (`name!(..)`) as edges.
+    pub include_macros: bool,
+    /// Drop test functions (`#[test]`, `test_*`, test modules/classes) and their edges.
+    pub exclude_tests: bool,
+    /// Ignore `name(` calls inside a function once it binds a local variable called `name`.
+    pub definition_only_edges: bool,
+    /// Record calls through imported modules (`utils.helper()`) as edges to external nodes.
+    pub include_external: bool,
+    /// Record bare mentions of a function (`register(callback)`) as reference edges.
+    pub include_references: bool,
+    /// Skip the signature and column capture that only annotates the graph (`--edges-only`).
+    pub edges_only: bool,
+    /// Match calls to defined names ignoring ASCII case (`HELPER()` calls `helper`).
+    pub ignore_case_calls: bool,
+    /// Attribute calls outside every function to a `<module>` node instead of dropping them.
+    pub module_node: bool,
+    /// Keep external calls into the standard library (the language's builtins list).
+    pub show_stdlib: bool,
+    /// Replaces the language's builtins list, for `--include-external` without `--show-stdlib`.
+    pub builtins: Option<Vec<String>>,
+    /// Glob patterns (relative to the walked directory) a file must match; empty means every supported file.
+    pub include: Vec<String>,
+    /// Glob patterns for files to skip; these win over `include`.
+    pub exclude: Vec<String>,
+}
+
+/// Worker threads for directory parsing unless `--threads` says otherwise.
+pub const DEFAULT_THREADS: usize = 8;
+/// `--max-file-size` default, in megabytes.
+pub const DEFAULT_MAX_FILE_SIZE_MB: usize = 50;
+/// `--max-functions` default.
+pub const DEFAULT_MAX_FUNCTIONS: usize = 100_000;
+/// `--max-line-length` default, in characters.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 50_000;
+
+/// The CLI's defaults, except that the cache is off: library callers opt in with
+/// `Config { enable_cache: true, ..Config::default() }` rather than finding cache files next to
+/// the sources they parse.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enable_cache: false,
+            cache_ttl: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE_MB * 1024 * 1024,
+            max_functions: DEFAULT_MAX_FUNCTIONS,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            json_pretty: false,
+            mmap: false,
+            threads: DEFAULT_THREADS,
+            merge_strategy: MergeStrategy::Qualify,
+            def_keyword: None,
+            body_open: None,
+            custom_langs: Vec::new(),
+            include_macros: false,
+            exclude_tests: false,
+            definition_only_edges: false,
+            include_external: false,
+            include_references: false,
+            edges_only: false,
+            ignore_case_calls: false,
+            module_node: true,
+            show_stdlib: false,
+            builtins: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Fingerprint of the options that change parse results, stored alongside cache entries.
+    pub fn cache_key(&self) -> u64 {
+        crate::cache::hash_string(&format!(
+            "macros={} exclude_tests={} definition_only_edges={} include_external={} include_references={} edges_only={} ignore_case_calls={} module_node={} show_stdlib={} builtins={:?}",
+            self.include_macros,
+            self.exclude_tests,
+            self.definition_only_edges,
+            self.include_external,
+            self.include_references,
+            self.edges_only,
+            self.ignore_case_calls,
+            self.module_node,
+            self.show_stdlib,
+            self.builtins
+        ))
+    }
 }
 
 impl From<&Cli> for Config {
     fn from(cli: &Cli) -> Self {
         Self {
             enable_cache: !cli.no_cache,
+            cache_ttl: cli.cache_ttl,
+            max_file_size: cli.max_file_size_mb.saturating_mul(1024 * 1024),
+            max_functions: cli.max_functions,
+            max_line_length: cli.max_line_length,
+            json_pretty: cli.json_pretty,
+            mmap: cli.mmap,
+            threads: cli.threads,
+            merge_strategy: cli.merge_strategy,
+            def_keyword: cli.def_keyword.clone(),
+            body_open: cli.body_open.clone(),
+            custom_langs: Vec::new(),
+            include_macros: cli.include_macros,
+            exclude_tests: cli.exclude_tests,
+            definition_only_edges: cli.definition_only_edges,
+            include_external: cli.include_external,
+            include_references: cli.include_references,
+            edges_only: cli.edges_only,
+            ignore_case_calls: cli.ignore_case_calls,
+            module_node: !cli.no_module_node,
+            show_stdlib: cli.show_stdlib,
+            builtins: cli.builtins.clone(),
+            include: cli.include.clone(),
+            exclude: cli.exclude.clone(),
         }
     }
 }
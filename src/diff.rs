@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::FnInfo;
+use crate::config::Config;
+use crate::error::ParseError;
+use crate::parser::{collect_source_files, parse_files};
+
+/// Lines (0-based, inclusive) touched in each file, keyed by absolute path.
+pub type ChangedLines = HashMap<PathBuf, Vec<(usize, usize)>>;
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, ParseError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| ParseError::GitError(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(ParseError::GitError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the new-file side of a hunk header, `@@ -a,b +start,count @@`.
+fn hunk_range(header: &str) -> Option<(usize, usize)> {
+    let new_side = header.split_whitespace().find(|part| part.starts_with('+'))?;
+    let (start, count) = match new_side[1..].split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+        None => (new_side[1..].parse::<usize>().ok()?, 1),
+    };
+    // A pure deletion (`+start,0`) sits just after line `start`; count it as touching that line.
+    let first = start.saturating_sub(1);
+    Some((first, first + count.saturating_sub(1)))
+}
+
+/// The path of a `+++ b/<path>` header line. Git ends a path containing spaces with a tab and,
+/// even with `core.quotePath` off, C-quotes one with a `"`, `\\` or control character in it.
+fn new_file_path(header: &str) -> Option<String> {
+    let path = header.strip_suffix('\t').unwrap_or(header);
+    let Some(quoted) = path.strip_prefix('"').and_then(|path| path.strip_suffix('"')) else {
+        return Some(path.to_string());
+    };
+    let mut bytes = Vec::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match chars.next()? {
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'f' => bytes.push(0x0c),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            'v' => bytes.push(0x0b),
+            // Three octal digits: one byte of a multi-byte character.
+            digit @ '0'..='3' => {
+                let octal: String = std::iter::once(digit).chain(chars.by_ref().take(2)).collect();
+                bytes.push(u8::from_str_radix(&octal, 8).ok()?);
+            }
+            other => bytes.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Collects the changed line ranges of a `git diff --unified=0` of the repository at `root`.
+fn parse_diff(root: &Path, diff: &str) -> ChangedLines {
+    let mut changed = ChangedLines::new();
+    let mut current: Option<PathBuf> = None;
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("+++ ") {
+            // Deleted files show up as `+++ /dev/null`.
+            current = new_file_path(header).and_then(|path| path.strip_prefix("b/").map(|relative| root.join(relative)));
+        } else if line.starts_with("@@")
+            && let Some(path) = &current
+            && let Some(range) = hunk_range(line)
+        {
+            changed.entry(path.clone()).or_default().push(range);
+        }
+    }
+    changed
+}
+
+/// Runs `git diff <git_ref>` in the repository containing `dir` and collects the changed line ranges.
+pub fn changed_lines(dir: &Path, git_ref: &str) -> Result<ChangedLines, ParseError> {
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim());
+    // Fixed prefixes and unquoted paths, whatever the user's diff settings.
+    let diff = git(
+        dir,
+        &["-c", "core.quotePath=false", "diff", "--unified=0", "--no-color", "--no-ext-diff", "--src-prefix=a/", "--dst-prefix=b/", git_ref, "--"],
+    )?;
+    Ok(parse_diff(&root, &diff))
+}
+
+/// Names of the functions whose span (definition to last body line) overlaps the changed lines
+/// of their own file, with `ranges` keyed by the functions' `source_file`.
+pub fn changed_functions(functions: &HashMap<String, FnInfo>, ranges: &ChangedLines) -> HashSet<String> {
+    functions
+        .iter()
+        .filter(|(_, info)| {
+            let Some(ranges) = info.source_file.as_ref().and_then(|file| ranges.get(file)) else {
+                return false;
+            };
+            let end = info.end_line.max(info.line_at_call);
+            ranges.iter().any(|&(first, last)| first <= end && info.line_at_call <= last)
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Parses only the files under `path` that changed since `git_ref`.
+///
+/// Returns the merged map of those files and the keys of the functions whose lines the diff touches.
+pub fn parse_since(path: &Path, git_ref: &str, config: &Config) -> Result<(HashMap<String, FnInfo>, HashSet<String>), ParseError> {
+    let (dir, candidates) = if path.is_dir() {
+        (path.to_path_buf(), collect_source_files(path, config)?)
+    } else {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        (parent.to_path_buf(), vec![path.to_path_buf()])
+    };
+    let lines = changed_lines(&dir, git_ref)?;
+
+    // Re-keyed by the paths the parse records as each function's `source_file`.
+    let mut files = Vec::new();
+    let mut ranges = ChangedLines::new();
+    for file in candidates {
+        if let Some(file_ranges) = std::fs::canonicalize(&file).ok().and_then(|abs| lines.get(&abs)) {
+            ranges.insert(file.clone(), file_ranges.clone());
+            files.push(file);
+        }
+    }
+
+    let functions = parse_files(&dir, &files, config)?;
+    // Names as the merge left them, `<relative/path>::<name>` where it qualified one.
+    let changed = changed_functions(&functions, &ranges);
+    Ok((functions, changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tab_ended_and_quoted_paths() {
+        let diff = "\
+diff --git a/my file.py b/my file.py
+--- a/my file.py\t
++++ b/my file.py\t
+@@ -3,0 +4,2 @@ def main():
+diff --git a/caf\u{e9}.py b/caf\u{e9}.py
+--- a/caf\u{e9}.py
++++ b/caf\u{e9}.py
+@@ -1 +1 @@
+diff --git \"a/say \\\"hi\\\".py\" \"b/say \\\"hi\\\".py\"
++++ \"b/say \\\"hi\\\".py\"
+@@ -2 +2 @@
++++ \"b/caf\\303\\251/x.py\"
+@@ -5,0 +6 @@
+--- a/gone.py
++++ /dev/null
+@@ -1,3 +0,0 @@
+";
+        let root = Path::new("/repo");
+        let changed = parse_diff(root, diff);
+        assert_eq!(changed[&root.join("my file.py")], [(3, 4)]);
+        assert_eq!(changed[&root.join("caf\u{e9}.py")], [(0, 0)]);
+        assert_eq!(changed[&root.join("say \"hi\".py")], [(1, 1)]);
+        assert_eq!(changed[&root.join("caf\u{e9}/x.py")], [(5, 5)]);
+        assert_eq!(changed.len(), 4);
+    }
+}
@@ -4,6 +4,14 @@ pub enum ParseError {
     CacheError(String),
     ParseFailure(String),
     UnsupportedLanguage(String),
+    InvalidLangFile(String),
+    DuplicateName(String),
+    InvalidGlob(String),
+    GitError(String),
+    FileTooLarge(String),
+    TooManyFunctions(String),
+    LineTooLong(String),
+    InvalidArchive(String),
 }
 
 impl From<std::io::Error> for ParseError {
@@ -19,6 +27,14 @@ impl std::fmt::Display for ParseError {
             ParseError::CacheError(e) => write!(f, "Cache error: {}", e),
             ParseError::ParseFailure(e) => write!(f, "Parse error: {}", e),
             ParseError::UnsupportedLanguage(e) => write!(f, "Language is not supported yet: {}", e),
+            ParseError::InvalidLangFile(e) => write!(f, "Invalid language file: {}", e),
+            ParseError::DuplicateName(e) => write!(f, "Duplicate function name: {}", e),
+            ParseError::InvalidGlob(e) => write!(f, "Invalid glob pattern: {}", e),
+            ParseError::GitError(e) => write!(f, "Git error: {}", e),
+            ParseError::FileTooLarge(e) => write!(f, "File too large: {}", e),
+            ParseError::TooManyFunctions(e) => write!(f, "Too many functions: {}", e),
+            ParseError::LineTooLong(e) => write!(f, "Line too long: {}", e),
+            ParseError::InvalidArchive(e) => write!(f, "Invalid archive: {}", e),
         }
     }
 }
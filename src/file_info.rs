@@ -1,12 +1,76 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Language {
     Py,
     Rs,
+    R,
+    Ex,
+    Hs,
+    Clj,
+    Pl,
+    Zig,
+    Ml,
+    Nim,
+    Dart,
+    Jl,
+    Objc,
     Unknown,
 }
 
+impl Language {
+    /// Every supported language, in the order they were added.
+    pub const ALL: [Language; 13] = [
+        Language::Py,
+        Language::Rs,
+        Language::R,
+        Language::Ex,
+        Language::Hs,
+        Language::Clj,
+        Language::Pl,
+        Language::Zig,
+        Language::Ml,
+        Language::Nim,
+        Language::Dart,
+        Language::Jl,
+        Language::Objc,
+    ];
+
+    /// File extensions (without the dot) parsed as this language; none for `Unknown`.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Language::Py => &["py"],
+            Language::Rs => &["rs"],
+            Language::R => &["R", "r"],
+            Language::Ex => &["ex", "exs"],
+            Language::Hs => &["hs"],
+            Language::Clj => &["clj"],
+            Language::Pl => &["pl", "pm"],
+            Language::Zig => &["zig"],
+            Language::Ml => &["ml", "fs"],
+            Language::Nim => &["nim"],
+            Language::Dart => &["dart"],
+            Language::Jl => &["jl"],
+            Language::Objc => &["m", "h"],
+            Language::Unknown => &[],
+        }
+    }
+
+    /// The language files with extension `ext` (without the dot) are parsed as.
+    pub fn from_extension(ext: &str) -> Self {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.extensions().contains(&ext))
+            .unwrap_or(Language::Unknown)
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(Language::Unknown, Language::from_extension)
+    }
+}
+
 #[derive(Debug)]
 pub struct FileInfo<'a> {
     pub file_type: Language,
@@ -18,11 +82,7 @@ impl<'a> FileInfo<'a> {
     pub fn from_path(path: &'a PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let metadata = std::fs::metadata(path)?;
 
-        let file_type = match path.extension().and_then(|ext| ext.to_str()) {
-            Some("py") => Language::Py,
-            Some("rs") => Language::Rs,
-            _ => Language::Unknown,
-        };
+        let file_type = Language::from_path(path);
 
         Ok(FileInfo {
             file_type,
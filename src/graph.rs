@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use crate::{FnInfo, callers, find_cycles, find_roots};
+
+/// Read-only navigation over a parsed call graph: roots, callees and callers by name.
+pub struct CallGraph<'a> {
+    functions: &'a HashMap<String, FnInfo>,
+    callers: HashMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> CallGraph<'a> {
+    pub fn new(functions: &'a HashMap<String, FnInfo>) -> Self {
+        let mut callers = callers(functions);
+        for list in callers.values_mut() {
+            list.sort();
+        }
+        Self { functions, callers }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a FnInfo> {
+        self.functions.get(name)
+    }
+
+    /// Functions nobody calls, sorted by name.
+    pub fn roots(&self) -> Vec<String> {
+        find_roots(self.functions)
+    }
+
+    /// Functions called by `name`, in call order.
+    pub fn callees(&self, name: &str) -> Vec<&'a str> {
+        self.functions
+            .get(name)
+            .map(|info| info.callees.iter().map(|(callee, _)| callee.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Functions that call `name`, sorted by name.
+    pub fn callers(&self, name: &str) -> &[&'a str] {
+        self.callers.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Length of the longest call chain below `name`: 0 for a function calling nothing defined,
+    /// 1 when it only calls such leaves, and so on. Each function of a cycle counts once, so a
+    /// set of functions calling each other adds one less than its size to the chains through it.
+    pub fn subtree_depth(&self, name: &str) -> usize {
+        let components = Components::new(self.functions);
+        match self.functions.get_key_value(name) {
+            Some((name, _)) => self.depth_of(components.of[name.as_str()], &components, &mut HashMap::new()),
+            None => 0,
+        }
+    }
+
+    /// [`Self::subtree_depth`] of every function, sharing the work between them.
+    pub fn subtree_depths(&self) -> HashMap<&'a str, usize> {
+        let components = Components::new(self.functions);
+        let mut depths = HashMap::new();
+        self.functions
+            .keys()
+            .map(|name| (name.as_str(), self.depth_of(components.of[name.as_str()], &components, &mut depths)))
+            .collect()
+    }
+
+    /// Depth below the component at `idx` of the call graph with its cycles collapsed, which has
+    /// none left, so each component's depth is worked out once.
+    fn depth_of(&self, idx: usize, components: &Components<'a>, depths: &mut HashMap<usize, usize>) -> usize {
+        if let Some(&depth) = depths.get(&idx) {
+            return depth;
+        }
+        let members = &components.members[idx];
+        let mut below = None;
+        for member in members {
+            for (callee, _) in &self.functions[*member].callees {
+                if let Some(&callee_idx) = components.of.get(callee.as_str())
+                    && callee_idx != idx
+                {
+                    below = below.max(Some(self.depth_of(callee_idx, components, depths)));
+                }
+            }
+        }
+        let depth = members.len() - 1 + below.map_or(0, |depth| depth + 1);
+        depths.insert(idx, depth);
+        depth
+    }
+}
+
+/// The strongly connected components of a call graph: functions calling each other, directly
+/// or not, share one; every other function has its own.
+struct Components<'a> {
+    members: Vec<Vec<&'a str>>,
+    of: HashMap<&'a str, usize>,
+}
+
+impl<'a> Components<'a> {
+    fn new(functions: &'a HashMap<String, FnInfo>) -> Self {
+        let mut members: Vec<Vec<&'a str>> = Vec::new();
+        let mut of = HashMap::new();
+        for cycle in find_cycles(functions) {
+            let cycle: Vec<&'a str> = cycle.iter().filter_map(|name| functions.get_key_value(name)).map(|(name, _)| name.as_str()).collect();
+            for name in &cycle {
+                of.insert(*name, members.len());
+            }
+            members.push(cycle);
+        }
+        for name in functions.keys() {
+            of.entry(name.as_str()).or_insert_with(|| {
+                members.push(vec![name.as_str()]);
+                members.len() - 1
+            });
+        }
+        Self { members, of }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depths_of_a_cycle_do_not_depend_on_where_it_was_entered() {
+        let functions: HashMap<String, FnInfo> = [("a", "b"), ("b", "a")]
+            .into_iter()
+            .map(|(name, callee)| (name.to_string(), FnInfo { callees: vec![(callee.to_string(), 0)], ..FnInfo::default() }))
+            .collect();
+        let graph = CallGraph::new(&functions);
+        let depths = graph.subtree_depths();
+        for name in ["a", "b"] {
+            assert_eq!(graph.subtree_depth(name), 1);
+            assert_eq!(depths[name], 1);
+        }
+    }
+
+    #[test]
+    fn depths_through_a_dense_cycle() {
+        // Twelve functions all calling each other, then a leaf: walking every simple path
+        // through them would take minutes.
+        let members: Vec<String> = (0..12).map(|i| format!("f{}", i)).collect();
+        let mut functions: HashMap<String, FnInfo> = members
+            .iter()
+            .map(|name| {
+                let callees = members.iter().chain(std::iter::once(&"leaf".to_string())).map(|callee| (callee.clone(), 0)).collect();
+                (name.clone(), FnInfo { callees, ..FnInfo::default() })
+            })
+            .collect();
+        functions.insert("main".to_string(), FnInfo { callees: vec![("f0".to_string(), 0)], ..FnInfo::default() });
+        functions.insert("leaf".to_string(), FnInfo::default());
+        let graph = CallGraph::new(&functions);
+        let depths = graph.subtree_depths();
+        assert_eq!(depths["leaf"], 0);
+        assert_eq!(depths["f5"], 12);
+        assert_eq!(depths["main"], 13);
+        assert_eq!(graph.subtree_depth("main"), 13);
+    }
+}
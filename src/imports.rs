@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use crate::FnInfo;
+use crate::config::Config;
+use crate::error::ParseError;
+use crate::file_info::{FileInfo, Language};
+use crate::lang::LangConfig;
+use crate::parser::{collect_source_files, lang_config};
+
+/// Builds the `--imports` graph of the file at `path`, or of every source file under it: a node
+/// per file (named by its path relative to the analyzed directory) with an edge to each module
+/// it imports. A module that is one of those files links to its node; any other becomes an
+/// `[external]` leaf named as imported. In directory mode, unreadable files are skipped with a warning.
+pub fn parse_imports(path: &Path, config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let (root, files) = if path.is_dir() {
+        (path, collect_source_files(path, config)?)
+    } else {
+        (path.parent().unwrap_or(Path::new("")), vec![path.to_path_buf()])
+    };
+
+    let mut parsed = Vec::new();
+    for file in &files {
+        match file_imports(file, config) {
+            Ok(imports) => parsed.push((file, imports)),
+            Err(e) if path.is_dir() => eprintln!("Skipping {}: {}", file.display(), e),
+            Err(e) => return Err(e),
+        }
+    }
+    let nodes: Vec<(String, Vec<String>)> = parsed
+        .iter()
+        .map(|(file, _)| {
+            let relative = file.strip_prefix(root).unwrap_or(file);
+            let name = relative.components().map(|part| part.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            (name, file_segments(relative))
+        })
+        .collect();
+
+    let mut graph: HashMap<String, FnInfo> = HashMap::new();
+    for ((file, imports), (name, _)) in parsed.iter().zip(&nodes) {
+        let mut callees: Vec<(String, usize)> = Vec::new();
+        for (module, line) in imports {
+            let local = resolve(module, &nodes);
+            // `crate::Name` or `from . import x` is in this package, but names no file of it.
+            if local.is_none() && is_self_reference(module) {
+                continue;
+            }
+            let target = local.unwrap_or(module);
+            if target == name || callees.iter().any(|(callee, _)| callee == target) {
+                continue;
+            }
+            callees.push((target.to_string(), *line));
+            if local.is_none() {
+                graph.entry(module.clone()).or_insert_with(|| FnInfo {
+                    line_at_call: *line,
+                    end_line: *line,
+                    is_external: true,
+                    ..FnInfo::default()
+                });
+            }
+        }
+        // A file shadows an external module spelled the same.
+        graph.insert(
+            name.clone(),
+            FnInfo {
+                callees,
+                source_file: Some(file.to_path_buf()),
+                ..FnInfo::default()
+            },
+        );
+    }
+    Ok(graph)
+}
+
+/// The modules `path` imports, with the 0-based line of each import, read a line at a time.
+fn file_imports(path: &PathBuf, config: &Config) -> Result<Vec<(String, usize)>, ParseError> {
+    let file_info = FileInfo {
+        file_type: Language::from_path(path),
+        file_path: path,
+        file_size: std::fs::metadata(path)?.len() as usize,
+    };
+    if file_info.file_size > config.max_file_size {
+        return Err(ParseError::FileTooLarge(format!(
+            "{} bytes, over the {} byte limit (--max-file-size)",
+            file_info.file_size, config.max_file_size
+        )));
+    }
+    let lang = lang_config(&file_info, config)?;
+
+    let mut imports = Vec::new();
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim_start_matches('\u{FEFF}').trim_start();
+        imports.extend(imported_modules(&lang, trimmed).into_iter().map(|module| (module, n)));
+    }
+    Ok(imports)
+}
+
+/// Modules or files named by the import statement `trimmed` (`use std::io;`, `#include <stdio.h>`,
+/// `import a, b as c`), as written; none if the line isn't one.
+fn imported_modules(lang: &LangConfig, trimmed: &str) -> Vec<String> {
+    let Some(keyword) = lang.import_keywords.iter().find(|keyword| {
+        trimmed
+            .strip_prefix(keyword.as_str())
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || "(\"'<".contains(c)))
+    }) else {
+        return Vec::new();
+    };
+    let rest = &trimmed[keyword.len()..];
+    let rest = lang.line_comments.iter().filter_map(|comment| rest.find(comment.as_str())).min().map_or(rest, |pos| &rest[..pos]);
+    let rest = rest.trim();
+
+    // Call-shaped (`library(dplyr)`) and quoted or bracketed paths (`#include <stdio.h>`,
+    // `import 'package:app/util.dart';`) name a single dependency.
+    let rest = rest.strip_prefix('(').map_or(rest, |inner| inner.split(')').next().unwrap_or_default().trim());
+    for (open, close) in [('"', '"'), ('\'', '\''), ('<', '>')] {
+        if let Some(inner) = rest.strip_prefix(open) {
+            return inner.split(close).next().filter(|path| !path.is_empty()).map(String::from).into_iter().collect();
+        }
+    }
+    // `from pkg.mod import name` depends on `pkg.mod`; a `from` without `import` is prose.
+    if keyword == "from" {
+        return rest.split_once(" import").map(|(module, _)| module.trim().to_string()).filter(|module| !module.is_empty()).into_iter().collect();
+    }
+
+    // What follows a brace group (`use std::{fs, io};`) or argument list (`use POSIX qw(floor)`)
+    // are names inside the module, not more modules.
+    let rest = rest.split(['{', '(']).next().unwrap_or_default();
+    let mut items: Vec<&str> = rest.split(',').collect();
+    // So is what follows a lone module's `: ` (Julia's `using Base: show`), while after a list
+    // the `: ` ends an option name (Elixir's `import Foo, only: [bar: 1]`).
+    if let Some(at) = items.iter().position(|item| item.contains(": ")) {
+        if at == 0 {
+            items = vec![items[0].split(": ").next().unwrap_or_default()];
+        } else {
+            items.truncate(at);
+        }
+    }
+    items
+        .into_iter()
+        .map(|item| {
+            let item = item.trim();
+            // Haskell's `import qualified Data.Map as M`.
+            let item = item.strip_prefix("qualified ").unwrap_or(item).trim_start();
+            item.split(|c: char| c.is_whitespace() || c == ';').next().unwrap_or_default().trim_end_matches([':', '.'])
+        })
+        .filter(|module| !module.is_empty() && module.chars().all(|c| lang.is_ident_char(c) || c == '.' || c == ':'))
+        .map(String::from)
+        .collect()
+}
+
+/// Whether `module` starts from the importing code's own package (`crate::`, `super::`, `.mod`).
+fn is_self_reference(module: &str) -> bool {
+    matches!(module.split(['/', '\\', ':', '.']).next(), Some("" | "crate" | "self" | "super"))
+}
+
+/// Parts of an imported module or path that can name a file: `crate::lang::LangConfig` gives
+/// `lang`, `LangConfig`; `app/util.dart` gives `app`, `util`. Self-references (`crate`, `self`,
+/// `super`, `package:`) and a source file extension are dropped.
+fn module_segments(module: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = module
+        .split(['/', '\\', ':', '.'])
+        .filter(|part| !part.is_empty() && !["crate", "self", "super", "package"].contains(part))
+        .collect();
+    if segments.len() > 1
+        && segments.last().is_some_and(|ext| *ext == "h" || Language::ALL.iter().any(|lang| lang.extensions().contains(ext)))
+    {
+        segments.pop();
+    }
+    segments
+}
+
+/// Directories and stem of a file, relative to the analyzed directory, for matching against
+/// [`module_segments`]; a module's own file (`mod.rs`, `__init__.py`) stands for its directory.
+fn file_segments(relative: &Path) -> Vec<String> {
+    let mut segments: Vec<String> = relative.with_extension("").components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+    if segments.len() > 1 && segments.last().is_some_and(|stem| stem == "mod" || stem == "__init__") {
+        segments.pop();
+    }
+    segments
+}
+
+/// The node of the file `module` names, if it is one of `nodes`: the file whose trailing path
+/// matches the most leading parts of the module (`pkg.util.helper` is `src/pkg/util.py`), the
+/// shortest path winning a tie.
+fn resolve<'n>(module: &str, nodes: &'n [(String, Vec<String>)]) -> Option<&'n str> {
+    let wanted = module_segments(module);
+    nodes
+        .iter()
+        .filter_map(|(name, file)| {
+            let matched = (1..=wanted.len().min(file.len())).rev().find(|&k| wanted[..k].iter().eq(&file[file.len() - k..]))?;
+            Some((matched, name))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.len().cmp(&a.1.len())).then_with(|| b.1.cmp(a.1)))
+        .map(|(_, name)| name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_statements_name_their_modules() {
+        let cases: &[(Language, &str, &[&str])] = &[
+            (Language::Rs, "use std::{fs, io};", &["std"]),
+            (Language::Rs, "use crate::lang::LangConfig; // config", &["crate::lang::LangConfig"]),
+            (Language::Rs, "user_count += 1;", &[]),
+            (Language::Py, "import os, sys as system", &["os", "sys"]),
+            (Language::Py, "from . import x", &["."]),
+            (Language::Py, "from pkg.util import helper", &["pkg.util"]),
+            (Language::Py, "from here on we loop", &[]),
+            (Language::Hs, "import qualified Data.Map as M", &["Data.Map"]),
+            (Language::Objc, "#include <stdio.h>", &["stdio.h"]),
+            (Language::Objc, "#import \"Util.h\"", &["Util.h"]),
+            (Language::Jl, "using Base: show", &["Base"]),
+            (Language::Ex, "import Foo, only: [bar: 1]", &["Foo"]),
+            (Language::R, "library(dplyr)", &["dplyr"]),
+            (Language::Dart, "import 'package:app/util.dart';", &["package:app/util.dart"]),
+        ];
+        for (language, line, expected) in cases {
+            let lang = LangConfig::try_from(language).unwrap();
+            assert_eq!(imported_modules(&lang, line), *expected, "{:?}: {}", language, line);
+        }
+    }
+
+    fn file_nodes(files: &[&str]) -> Vec<(String, Vec<String>)> {
+        files.iter().map(|file| (file.to_string(), file_segments(Path::new(file)))).collect()
+    }
+
+    #[test]
+    fn resolve_prefers_the_longest_match_then_the_shortest_path() {
+        let nodes = file_nodes(&["util.py", "pkg/util.py", "vendor/pkg/util.py", "other/util.py", "lang/mod.rs"]);
+        assert_eq!(resolve("pkg.util.helper", &nodes), Some("pkg/util.py"));
+        assert_eq!(resolve("util", &nodes), Some("util.py"));
+        assert_eq!(resolve("crate::lang::LangConfig", &nodes), Some("lang/mod.rs"));
+        assert_eq!(resolve("numpy", &nodes), None);
+
+        // Equally long paths with equally long matches: the first by name.
+        let tied = file_nodes(&["b/util.py", "a/util.py"]);
+        assert_eq!(resolve("util", &tied), Some("a/util.py"));
+    }
+}
@@ -1,45 +1,1255 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::Deserialize;
+use crate::error::ParseError;
+use crate::file_info::Language;
+use crate::Visibility;
+
 pub trait LangSpec {
-    const FUNC_DEF: &'static str;
+    /// Keywords that introduce a function (`def`; `def` and `defp` in Elixir).
+    const FUNC_DEFS: &'static [&'static str];
     const PARAMS_OPEN: &'static str;
     const PARAMS_CLOSE: &'static str;
     const END_DEF: &'static str;
+    /// Alternative to [`Self::END_DEF`] introducing a single-expression body (`=> expr;`).
+    const EXPRESSION_BODY: Option<&'static str> = None;
+    const LINE_COMMENTS: &'static [&'static str];
+    /// Tokens that may precede a call to mark it as a method/path call (`.`, `::`).
+    const ACCESSORS: &'static [&'static str];
+    /// Qualifiers that may precede `FUNC_DEFS` (`pub`, `async`, `unsafe`) and are skipped when extracting names.
+    const MODIFIERS: &'static [&'static str] = &[];
+    /// Keyword that defines a macro (`macro_rules!`), if the language has bang-invoked macros.
+    const MACRO_DEF: Option<&'static str> = None;
+    /// Whether functions inside `impl` blocks are keyed as `Type::name`.
+    const QUALIFY_IMPLS: bool = false;
+    /// Prefixes of attribute/decorator lines that may sit between a marker and its definition.
+    const ANNOTATION_PREFIXES: &'static [&'static str] = &[];
+    /// Lines (`#[test]`, `#[cfg(test)]`) marking the next definition or block as test code.
+    const TEST_MARKERS: &'static [&'static str] = &[];
+    /// Function-name prefixes (`test_`) that identify test functions.
+    const TEST_NAME_PREFIXES: &'static [&'static str] = &[];
+    /// Block headers (`class Test`) whose members are all test code.
+    const TEST_BLOCK_PREFIXES: &'static [&'static str] = &[];
+    /// Whether a definition may omit its parameter list entirely (`def name do`).
+    const OPTIONAL_PARAMS: bool = false;
+    /// Words that open a keyword-delimited block (`do`, `fn`), closed by [`Self::BLOCK_END`].
+    const BLOCK_OPENERS: &'static [&'static str] = &[];
+    /// Word closing a keyword-delimited block (`end`); bodies are then tracked by depth, not indentation.
+    const BLOCK_END: Option<&'static str> = None;
+    /// Block words inside brackets don't count (Julia's `a[end]`, `[f(x) for x in xs]`).
+    const BRACKETS_HIDE_BLOCK_WORDS: bool = false;
+    /// Keywords declaring a namespace (`defmodule`, `mod`, `class`) whose name prefixes the functions inside it.
+    const MODULE_DEFS: &'static [&'static str] = &[];
+    /// Whether a bodiless module header (`package Name;`) sets the namespace for the rest of the file.
+    const MODULE_STATEMENTS: bool = false;
+    /// Joins a module name to the names inside it (`MyApp.Accounts.create`).
+    const MODULE_SEPARATOR: &'static str = ".";
+    /// Definitions only start at column 0; indented lines that look like one are local bindings.
+    const TOP_LEVEL_ONLY: bool = false;
+    /// Bodies end where their `{`/`}` balance (braces in comments, strings and char literals
+    /// don't count) rather than at the next line indented no deeper than the header.
+    const BRACE_SCOPES: bool = false;
+    /// A `'` starts a lifetime (`'a`) unless it closes a char literal (`'{'`), and raw strings are
+    /// `r#"..."#`: the brace counting of [`Self::BRACE_SCOPES`] skips those by Rust's rules
+    /// instead of taking `'` as a quote like `"`.
+    const LIFETIMES: bool = false;
+    /// Calls need no parentheses (`f x`), so any whole-word use of a function's name counts.
+    const BARE_CALLS: bool = false;
+    /// Calls are Lisp forms, `(name args)`: the name follows an opening paren instead of preceding one.
+    const PREFIX_CALLS: bool = false;
+    /// Calls are bracketed message sends, `[receiver name:a other:b]`, matched against the whole
+    /// selector (`name:other:`) rather than a name followed by a parameter list.
+    const MESSAGE_CALLS: bool = false;
+    /// Decorators that change what kind of function a definition is (`property`, `staticmethod`),
+    /// recorded for display; when several are stacked, the one listed first wins.
+    const KIND_DECORATORS: &'static [&'static str] = &[];
+    /// Parameter defaults are expressions evaluated with the definition (`def f(x=helper()):`),
+    /// so calls in the parameter list are edges from the function being defined.
+    const DEFAULT_ARGS: bool = false;
+    /// Code outside definitions runs when the file is loaded (a script's top level), so its calls
+    /// are graphed from a `<module>` node; elsewhere the top level only declares things.
+    const TOP_LEVEL_CODE: bool = false;
+    /// Module-level lines opening a script's entrypoint block (`if __name__ == "__main__":`), which
+    /// is graphed as a function named `__main__` calling what the block calls.
+    const MAIN_GUARDS: &'static [&'static str] = &[];
+    /// Anonymous-function keywords (`lambda`): a top-level `name = lambda ..` defines `name`.
+    const LAMBDA_KEYWORDS: &'static [&'static str] = &[];
+    /// Characters besides letters, digits and `_` that may appear in identifiers (`?!` in Elixir, `.` in R).
+    const EXTRA_IDENT_CHARS: &'static str = "";
+    /// Statements binding a module name that then qualifies calls (`import utils` .. `utils.helper()`).
+    const IMPORTS: &'static [&'static str] = &[];
+    /// Keywords of statements naming a module or file this one depends on (`use`, `#include`),
+    /// graphed by `--imports`.
+    const IMPORT_KEYWORDS: &'static [&'static str] = &[];
+    /// Standard-library modules and builtins (`os`, `print`) whose calls `--include-external`
+    /// leaves out unless `--show-stdlib` is given.
+    const BUILTINS: &'static [&'static str] = &[];
+    /// Prefixes of a local variable binding (`let `, or `""` for a bare `name = ..`).
+    const LOCAL_BINDINGS: &'static [&'static str] = &[];
 
     fn is_valid_identifier(name: &str) -> bool;
+
+    fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || Self::EXTRA_IDENT_CHARS.contains(c)
+    }
+
+    /// Returns the defined function's name if the (trimmed) line starts a definition.
+    ///
+    /// The default handles `<keyword> name(` for each of `FUNC_DEFS`; languages with other shapes override it.
+    fn extract_name(def_line: &str) -> Option<String> {
+        keyword_name(strip_modifiers(def_line, Self::MODIFIERS), Self::FUNC_DEFS, Self::PARAMS_OPEN, Self::is_valid_identifier)
+    }
+
+    /// Who can call the function defined by `def_line` (trimmed) under `name`; everything is
+    /// public in languages without a visibility convention.
+    fn visibility(_def_line: &str, _name: &str) -> Visibility {
+        Visibility::Public
+    }
+}
+
+/// The leading-underscore convention (`_helper`), with dunders (`__init__`) still public.
+fn underscore_visibility(name: &str) -> Visibility {
+    let dunder = name.len() > 4 && name.starts_with("__") && name.ends_with("__");
+    if name.starts_with('_') && !dunder { Visibility::Private } else { Visibility::Public }
+}
+
+/// Runtime description of a language, mirroring [`LangSpec`] so it can be overridden from the CLI.
+#[derive(Debug, Clone)]
+pub struct LangConfig {
+    pub func_defs: Vec<String>,
+    pub params_open: String,
+    pub params_close: String,
+    pub end_def: String,
+    pub expression_body: Option<String>,
+    pub line_comments: Vec<String>,
+    pub accessors: Vec<String>,
+    pub modifiers: Vec<String>,
+    pub qualify_impls: bool,
+    pub macro_def: Option<String>,
+    pub annotation_prefixes: Vec<String>,
+    pub test_markers: Vec<String>,
+    pub test_name_prefixes: Vec<String>,
+    pub test_block_prefixes: Vec<String>,
+    pub optional_params: bool,
+    pub block_openers: Vec<String>,
+    pub block_end: Option<String>,
+    pub brackets_hide_block_words: bool,
+    pub module_defs: Vec<String>,
+    pub module_statements: bool,
+    pub module_separator: String,
+    pub local_bindings: Vec<String>,
+    pub imports: Vec<String>,
+    pub import_keywords: Vec<String>,
+    pub builtins: Vec<String>,
+    pub top_level_only: bool,
+    pub brace_scopes: bool,
+    pub lifetimes: bool,
+    pub bare_calls: bool,
+    pub prefix_calls: bool,
+    pub message_calls: bool,
+    pub top_level_code: bool,
+    pub main_guards: Vec<String>,
+    pub lambda_keywords: Vec<String>,
+    pub default_args: bool,
+    pub kind_decorators: Vec<String>,
+    pub extra_ident_chars: String,
+    pub is_valid_identifier: fn(&str) -> bool,
+    /// Returns the defined function's name if the (trimmed) line starts a definition.
+    pub extract_name: fn(&LangConfig, &str) -> Option<String>,
+    /// Visibility of a definition from its (trimmed) line and name.
+    pub visibility: fn(&str, &str) -> Visibility,
+    /// File extensions (without the dot) this config applies to; only used by language files.
+    pub extensions: Vec<String>,
+}
+
+impl LangConfig {
+    pub fn from_spec<L: LangSpec>() -> Self {
+        Self {
+            func_defs: to_strings(L::FUNC_DEFS),
+            params_open: L::PARAMS_OPEN.to_string(),
+            params_close: L::PARAMS_CLOSE.to_string(),
+            end_def: L::END_DEF.to_string(),
+            expression_body: L::EXPRESSION_BODY.map(str::to_string),
+            line_comments: to_strings(L::LINE_COMMENTS),
+            accessors: to_strings(L::ACCESSORS),
+            modifiers: to_strings(L::MODIFIERS),
+            qualify_impls: L::QUALIFY_IMPLS,
+            macro_def: L::MACRO_DEF.map(str::to_string),
+            annotation_prefixes: to_strings(L::ANNOTATION_PREFIXES),
+            test_markers: to_strings(L::TEST_MARKERS),
+            test_name_prefixes: to_strings(L::TEST_NAME_PREFIXES),
+            test_block_prefixes: to_strings(L::TEST_BLOCK_PREFIXES),
+            optional_params: L::OPTIONAL_PARAMS,
+            block_openers: to_strings(L::BLOCK_OPENERS),
+            block_end: L::BLOCK_END.map(str::to_string),
+            brackets_hide_block_words: L::BRACKETS_HIDE_BLOCK_WORDS,
+            module_defs: to_strings(L::MODULE_DEFS),
+            module_statements: L::MODULE_STATEMENTS,
+            module_separator: L::MODULE_SEPARATOR.to_string(),
+            local_bindings: to_strings(L::LOCAL_BINDINGS),
+            imports: to_strings(L::IMPORTS),
+            import_keywords: to_strings(L::IMPORT_KEYWORDS),
+            builtins: to_strings(L::BUILTINS),
+            top_level_only: L::TOP_LEVEL_ONLY,
+            brace_scopes: L::BRACE_SCOPES,
+            lifetimes: L::LIFETIMES,
+            bare_calls: L::BARE_CALLS,
+            prefix_calls: L::PREFIX_CALLS,
+            message_calls: L::MESSAGE_CALLS,
+            top_level_code: L::TOP_LEVEL_CODE,
+            main_guards: to_strings(L::MAIN_GUARDS),
+            lambda_keywords: to_strings(L::LAMBDA_KEYWORDS),
+            default_args: L::DEFAULT_ARGS,
+            kind_decorators: to_strings(L::KIND_DECORATORS),
+            extra_ident_chars: L::EXTRA_IDENT_CHARS.to_string(),
+            is_valid_identifier: L::is_valid_identifier,
+            extract_name: |_, def_line| L::extract_name(def_line),
+            visibility: L::visibility,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// A brace-bodied, C-like language introduced by `func_def`, used for files with no built-in spec.
+    pub fn generic(func_def: &str) -> Self {
+        Self {
+            func_defs: vec![func_def.to_string()],
+            params_open: "(".to_string(),
+            params_close: ")".to_string(),
+            end_def: "{".to_string(),
+            expression_body: None,
+            line_comments: vec!["//".to_string()],
+            accessors: vec![".".to_string()],
+            modifiers: Vec::new(),
+            qualify_impls: false,
+            macro_def: None,
+            annotation_prefixes: Vec::new(),
+            test_markers: Vec::new(),
+            test_name_prefixes: Vec::new(),
+            test_block_prefixes: Vec::new(),
+            optional_params: false,
+            block_openers: Vec::new(),
+            block_end: None,
+            brackets_hide_block_words: false,
+            module_defs: Vec::new(),
+            module_statements: false,
+            module_separator: ".".to_string(),
+            local_bindings: Vec::new(),
+            imports: Vec::new(),
+            import_keywords: Vec::new(),
+            builtins: Vec::new(),
+            top_level_only: false,
+            brace_scopes: false,
+            lifetimes: false,
+            bare_calls: false,
+            prefix_calls: false,
+            message_calls: false,
+            top_level_code: true,
+            main_guards: Vec::new(),
+            lambda_keywords: Vec::new(),
+            default_args: false,
+            kind_decorators: Vec::new(),
+            extra_ident_chars: String::new(),
+            is_valid_identifier: py::Python::is_valid_identifier,
+            extract_name: extract_keyword_name,
+            visibility: |_, _| Visibility::Public,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Whether `c` can be part of an identifier, so a name next to it is not a whole word.
+    pub fn is_ident_char(&self, c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || self.extra_ident_chars.contains(c)
+    }
+
+    pub fn is_comment(&self, trimmed: &str) -> bool {
+        self.line_comments.iter().any(|prefix| trimmed.starts_with(prefix.as_str()))
+    }
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+pub(crate) fn starts_with_keyword(trimmed: &str, keyword: &str) -> bool {
+    trimmed
+        .strip_prefix(keyword)
+        .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+}
+
+/// Skips leading qualifiers such as `pub`, `pub(crate)` or `async`, in any order.
+fn strip_modifiers<'a>(mut def_line: &'a str, modifiers: &[impl AsRef<str>]) -> &'a str {
+    while let Some(rest) = modifiers.iter().find_map(|modifier| {
+        def_line
+            .strip_prefix(modifier.as_ref())
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+    }) {
+        def_line = rest.trim_start();
+    }
+    def_line
+}
+
+/// The first of `keywords` that `def_line` starts with, as a whole word.
+pub(crate) fn leading_keyword<'a, K: AsRef<str>>(def_line: &str, keywords: &'a [K]) -> Option<&'a str> {
+    keywords.iter().map(AsRef::as_ref).find(|keyword| starts_with_keyword(def_line, keyword))
+}
+
+/// The usual `<keyword> name(` form: the name follows one of the keywords and runs up to the
+/// parameters (or a generic parameter list, `name<T>(`).
+fn keyword_name(def_line: &str, keywords: &[impl AsRef<str>], params_open: &str, is_valid_identifier: fn(&str) -> bool) -> Option<String> {
+    let keyword = leading_keyword(def_line, keywords)?;
+    let after_def = def_line[keyword.len()..].trim();
+
+    if let Some(paren_pos) = after_def.find(params_open) {
+        let name = after_def[..paren_pos].split('<').next().unwrap_or_default().trim();
+        if !name.is_empty() && is_valid_identifier(name) {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// The assignment form: the name is the target of one of `operators` (`<-`, `=`) whose value
+/// starts with `keyword`, as in R's `name <- function(` or Python's `name = lambda x: ..`.
+pub fn assigned_name(
+    def_line: &str,
+    keyword: &str,
+    operators: &[&str],
+    is_ident_char: impl Fn(char) -> bool,
+    is_valid_identifier: fn(&str) -> bool,
+) -> Option<String> {
+    let keyword_pos = def_line.match_indices(keyword).map(|(pos, _)| pos).find(|&pos| {
+        !def_line[..pos].ends_with(&is_ident_char)
+            && !def_line[pos + keyword.len()..].starts_with(&is_ident_char)
+    })?;
+
+    let before = def_line[..keyword_pos].trim_end();
+    let target = operators.iter().find_map(|operator| before.strip_suffix(operator))?;
+    let name = target.trim().trim_matches('`');
+
+    is_valid_identifier(name).then(|| name.to_string())
+}
+
+/// Position of the first `target` outside generic brackets (`Map<K, V> name(`).
+fn generic_depth_find(text: &str, target: char) -> Option<usize> {
+    let mut depth = 0;
+    text.char_indices().find_map(|(pos, c)| {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ if c == target && depth == 0 => return Some(pos),
+            _ => {}
+        }
+        None
+    })
+}
+
+/// Position of the `<` opening the generic list that `text` ends with (`text` minus its closing `>`).
+fn generic_depth_rfind(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    text.char_indices().rev().find_map(|(pos, c)| {
+        match c {
+            '>' => depth += 1,
+            '<' if depth == 0 => return Some(pos),
+            '<' => depth -= 1,
+            _ => {}
+        }
+        None
+    })
+}
+
+/// [`LangSpec::extract_name`]'s default, driven by a runtime config (custom keywords, language files).
+pub fn extract_keyword_name(lang: &LangConfig, def_line: &str) -> Option<String> {
+    keyword_name(strip_modifiers(def_line, &lang.modifiers), &lang.func_defs, &lang.params_open, lang.is_valid_identifier)
+}
+
+/// One `[language.<name>]` table in a `--lang-file`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LangDef {
+    keyword: String,
+    body_open: String,
+    extensions: Vec<String>,
+    #[serde(default = "default_params_open")]
+    params_open: String,
+    #[serde(default = "default_params_close")]
+    params_close: String,
+    #[serde(default)]
+    line_comments: Vec<String>,
+    #[serde(default = "default_accessors")]
+    accessors: Vec<String>,
+    #[serde(default)]
+    qualify_impls: bool,
+    #[serde(default)]
+    extra_ident_chars: String,
+    #[serde(default)]
+    builtins: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LangFile {
+    #[serde(default)]
+    language: BTreeMap<String, LangDef>,
+}
+
+fn default_params_open() -> String {
+    "(".to_string()
+}
+
+fn default_params_close() -> String {
+    ")".to_string()
+}
+
+fn default_accessors() -> Vec<String> {
+    vec![".".to_string()]
+}
+
+impl LangDef {
+    fn into_config(self, name: &str) -> Result<LangConfig, ParseError> {
+        let invalid = |reason: &str| ParseError::InvalidLangFile(format!("language `{}`: {}", name, reason));
+
+        for (field, value) in [
+            ("keyword", &self.keyword),
+            ("body_open", &self.body_open),
+            ("params_open", &self.params_open),
+            ("params_close", &self.params_close),
+        ] {
+            if value.trim().is_empty() {
+                return Err(invalid(&format!("`{}` must not be empty", field)));
+            }
+        }
+        if self.keyword.contains(char::is_whitespace) {
+            return Err(invalid("`keyword` must be a single word"));
+        }
+
+        let extensions: Vec<String> = self
+            .extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_string())
+            .collect();
+        if extensions.is_empty() || extensions.iter().any(|ext| ext.is_empty()) {
+            return Err(invalid("`extensions` must list at least one non-empty extension"));
+        }
+
+        Ok(LangConfig {
+            func_defs: vec![self.keyword],
+            params_open: self.params_open,
+            params_close: self.params_close,
+            end_def: self.body_open,
+            expression_body: None,
+            line_comments: self.line_comments,
+            accessors: self.accessors,
+            modifiers: Vec::new(),
+            qualify_impls: self.qualify_impls,
+            prefix_calls: false,
+            message_calls: false,
+            top_level_code: true,
+            main_guards: Vec::new(),
+            lambda_keywords: Vec::new(),
+            default_args: false,
+            kind_decorators: Vec::new(),
+            extra_ident_chars: self.extra_ident_chars,
+            macro_def: None,
+            annotation_prefixes: Vec::new(),
+            test_markers: Vec::new(),
+            test_name_prefixes: Vec::new(),
+            test_block_prefixes: Vec::new(),
+            optional_params: false,
+            block_openers: Vec::new(),
+            block_end: None,
+            brackets_hide_block_words: false,
+            module_defs: Vec::new(),
+            module_statements: false,
+            module_separator: ".".to_string(),
+            local_bindings: Vec::new(),
+            imports: Vec::new(),
+            import_keywords: Vec::new(),
+            builtins: self.builtins,
+            top_level_only: false,
+            brace_scopes: false,
+            lifetimes: false,
+            bare_calls: false,
+            is_valid_identifier: py::Python::is_valid_identifier,
+            extract_name: extract_keyword_name,
+            visibility: |_, _| Visibility::Public,
+            extensions,
+        })
+    }
+}
+
+/// Reads user-defined languages from a TOML file of `[language.<name>]` tables.
+pub fn load_lang_file(path: &Path) -> Result<Vec<LangConfig>, ParseError> {
+    let content = std::fs::read_to_string(path)?;
+    let file: LangFile = toml::from_str(&content)
+        .map_err(|e| ParseError::InvalidLangFile(format!("{}: {}", path.display(), e)))?;
+
+    if file.language.is_empty() {
+        return Err(ParseError::InvalidLangFile(format!("{}: no [language.<name>] tables defined", path.display())));
+    }
+
+    file.language
+        .into_iter()
+        .map(|(name, def)| def.into_config(&name))
+        .collect()
+}
+
+impl TryFrom<&Language> for LangConfig {
+    type Error = ParseError;
+
+    fn try_from(language: &Language) -> Result<Self, Self::Error> {
+        match language {
+            Language::Py => Ok(Self::from_spec::<py::Python>()),
+            Language::Rs => Ok(Self::from_spec::<rs::Rust>()),
+            Language::R => Ok(Self::from_spec::<r::R>()),
+            Language::Ex => Ok(Self::from_spec::<ex::Elixir>()),
+            Language::Hs => Ok(Self::from_spec::<hs::Haskell>()),
+            Language::Clj => Ok(Self::from_spec::<clj::Clojure>()),
+            Language::Pl => Ok(Self::from_spec::<pl::Perl>()),
+            Language::Zig => Ok(Self::from_spec::<zig::Zig>()),
+            Language::Ml => Ok(Self::from_spec::<ml::OCaml>()),
+            Language::Nim => Ok(Self::from_spec::<nim::Nim>()),
+            Language::Dart => Ok(Self::from_spec::<dart::Dart>()),
+            Language::Jl => Ok(Self::from_spec::<jl::Julia>()),
+            Language::Objc => Ok(Self::from_spec::<objc::ObjectiveC>()),
+            Language::Unknown => Err(ParseError::UnsupportedLanguage("unknown".into())),
+        }
+    }
 }
 
 pub mod py {
     use super::LangSpec;
+    use crate::Visibility;
 
     pub struct Python;
 
     impl LangSpec for Python {
-        const FUNC_DEF: &'static str = "def";
+        const FUNC_DEFS: &'static [&'static str] = &["def"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["import", "from"];
         const PARAMS_OPEN: &'static str = "(";
         const PARAMS_CLOSE: &'static str = ")";
         const END_DEF: &'static str = ":";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const TOP_LEVEL_CODE: bool = true;
+        // Coroutines: `async def name(`
+        const MODIFIERS: &'static [&'static str] = &["async"];
+        const ANNOTATION_PREFIXES: &'static [&'static str] = &["@"];
+        const TEST_NAME_PREFIXES: &'static [&'static str] = &["test_"];
+        const TEST_BLOCK_PREFIXES: &'static [&'static str] = &["class Test"];
+        const MODULE_DEFS: &'static [&'static str] = &["class"];
+        const LAMBDA_KEYWORDS: &'static [&'static str] = &["lambda"];
+        const MAIN_GUARDS: &'static [&'static str] = &["if __name__ == \"__main__\":", "if __name__ == '__main__':"];
+        const DEFAULT_ARGS: bool = true;
+        const KIND_DECORATORS: &'static [&'static str] =
+            &["property", "cached_property", "classmethod", "staticmethod", "abstractmethod"];
+        const LOCAL_BINDINGS: &'static [&'static str] = &[""];
+        const IMPORTS: &'static [&'static str] = &["import"];
+        const BUILTINS: &'static [&'static str] = &[
+            "abc", "argparse", "asyncio", "builtins", "collections", "copy", "dataclasses", "datetime", "enum",
+            "functools", "glob", "io", "itertools", "json", "logging", "math", "operator", "os", "pathlib", "pickle",
+            "random", "re", "shutil", "string", "subprocess", "sys", "tempfile", "threading", "time", "typing",
+        ];
 
         fn is_valid_identifier(name: &str) -> bool {
-            name.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_')
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
                 && name.chars().all(|c| c.is_alphanumeric() || c == '_')
         }
+
+        fn visibility(_def_line: &str, name: &str) -> Visibility {
+            super::underscore_visibility(name)
+        }
     }
 }
 
 pub mod rs {
     use super::LangSpec;
+    use crate::Visibility;
 
     pub struct Rust;
 
     impl LangSpec for Rust {
-        const FUNC_DEF: &'static str = "fn";
+        const FUNC_DEFS: &'static [&'static str] = &["fn"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["use"];
         const PARAMS_OPEN: &'static str = "(";
         const PARAMS_CLOSE: &'static str = ")";
         const END_DEF: &'static str = "{";
+        const LINE_COMMENTS: &'static [&'static str] = &["//"];
+        const ACCESSORS: &'static [&'static str] = &[".", "::"];
+        const MODIFIERS: &'static [&'static str] = &[
+            "pub", "pub(crate)", "pub(super)", "pub(self)", "const", "async", "unsafe", "extern \"C\"", "extern", "default",
+        ];
+        const QUALIFY_IMPLS: bool = true;
+        const MACRO_DEF: Option<&'static str> = Some("macro_rules!");
+        const ANNOTATION_PREFIXES: &'static [&'static str] = &["#["];
+        const TEST_MARKERS: &'static [&'static str] = &["#[test]", "#[cfg(test)]", "#[tokio::test"];
+        const MODULE_DEFS: &'static [&'static str] = &["mod", "pub mod", "pub(crate) mod", "pub(super) mod"];
+        const MODULE_SEPARATOR: &'static str = "::";
+        const LOCAL_BINDINGS: &'static [&'static str] =
+            &["let mut ", "let ", "pub static ", "pub const ", "static mut ", "static ", "const "];
+        const BRACE_SCOPES: bool = true;
+        const LIFETIMES: bool = true;
 
         fn is_valid_identifier(name: &str) -> bool {
             // Very simplified Rust check
-            name.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_')
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// `pub` is public, `pub(crate)`/`pub(super)`/`pub(in ..)` crate-visible, anything else private.
+        fn visibility(def_line: &str, _name: &str) -> Visibility {
+            match def_line.strip_prefix("pub") {
+                Some(rest) if rest.starts_with('(') && !rest.starts_with("(self)") => Visibility::Crate,
+                Some(rest) if rest.starts_with(char::is_whitespace) => Visibility::Public,
+                _ => Visibility::Private,
+            }
+        }
+    }
+}
+
+pub mod r {
+    use super::{LangSpec, assigned_name};
+
+    pub struct R;
+
+    const KEYWORD: &str = "function";
+
+    impl LangSpec for R {
+        const FUNC_DEFS: &'static [&'static str] = &[KEYWORD];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["library", "require", "source"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "{";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["::", "$"];
+        const TOP_LEVEL_CODE: bool = true;
+        const EXTRA_IDENT_CHARS: &'static str = ".";
+        const DEFAULT_ARGS: bool = true;
+
+        fn is_valid_identifier(name: &str) -> bool {
+            // R names may contain dots (`print.summary`) and start with one if not followed by a digit
+            let mut chars = name.chars();
+            let valid_start = match chars.next() {
+                Some('.') => !chars.next().is_some_and(|c| c.is_ascii_digit()),
+                Some(c) => c.is_alphabetic(),
+                None => false,
+            };
+            valid_start && name.chars().all(Self::is_ident_char)
+        }
+
+        /// The name sits to the left of the assignment: `name <- function(`, `name <<- function(` or `name = function(`.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let fn_pos = def_line.find(KEYWORD)?;
+            if !def_line[fn_pos + KEYWORD.len()..].trim_start().starts_with(Self::PARAMS_OPEN) {
+                return None;
+            }
+            assigned_name(def_line, KEYWORD, &["<<-", "<-", "="], Self::is_ident_char, Self::is_valid_identifier)
+        }
+    }
+}
+
+pub mod ex {
+    use super::{LangSpec, leading_keyword};
+    use crate::Visibility;
+
+    pub struct Elixir;
+
+    impl LangSpec for Elixir {
+        const FUNC_DEFS: &'static [&'static str] = &["def", "defp"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["alias", "import", "require", "use"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "do";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const TOP_LEVEL_CODE: bool = true;
+        const OPTIONAL_PARAMS: bool = true;
+        const BLOCK_OPENERS: &'static [&'static str] = &["do", "fn"];
+        const BLOCK_END: Option<&'static str> = Some("end");
+        const MODULE_DEFS: &'static [&'static str] = &["defmodule"];
+        const EXTRA_IDENT_CHARS: &'static str = "?!";
+        const LOCAL_BINDINGS: &'static [&'static str] = &[""];
+
+        fn is_valid_identifier(name: &str) -> bool {
+            // Function names may end in `?` or `!` (`valid?`, `save!`)
+            let stem = name.strip_suffix(['?', '!']).unwrap_or(name);
+            stem.chars().next().is_some_and(|c| c.is_lowercase() || c == '_')
+                && stem.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// Public `def` and private `defp`, with or without a parameter list: `def name(a) do`, `defp name do`.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let keyword = leading_keyword(def_line, Self::FUNC_DEFS)?;
+            let name: String = def_line[keyword.len()..]
+                .trim_start()
+                .chars()
+                .take_while(|&c| Self::is_ident_char(c))
+                .collect();
+
+            Self::is_valid_identifier(&name).then_some(name)
+        }
+
+        fn visibility(def_line: &str, _name: &str) -> Visibility {
+            if leading_keyword(def_line, &["defp"]).is_some() { Visibility::Private } else { Visibility::Public }
+        }
+    }
+}
+
+pub mod hs {
+    use super::LangSpec;
+
+    pub struct Haskell;
+
+    /// Words that start a column-0 line without defining a function.
+    const RESERVED: &[&str] = &[
+        "module", "import", "data", "type", "newtype", "class", "instance", "deriving", "where", "let", "in",
+        "if", "then", "else", "case", "of", "do", "infix", "infixl", "infixr", "foreign", "default",
+    ];
+
+    impl LangSpec for Haskell {
+        // No keyword introduces a definition; `extract_name` looks for `name args =` instead.
+        const FUNC_DEFS: &'static [&'static str] = &[];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["import"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "=";
+        const LINE_COMMENTS: &'static [&'static str] = &["--"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const OPTIONAL_PARAMS: bool = true;
+        const TOP_LEVEL_ONLY: bool = true;
+        const BARE_CALLS: bool = true;
+        const EXTRA_IDENT_CHARS: &'static str = "'";
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_lowercase() || c == '_')
+                && name.chars().all(Self::is_ident_char)
+                && !RESERVED.contains(&name)
+        }
+
+        /// `name arg1 (x:xs) = ..`: a name followed by patterns and a lone `=`.
+        /// Type signatures (`name :: Int`) and operator definitions (`x <+> y = ..`) are skipped.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let (head, _) = def_line.split_once('=')?;
+            let rest = &def_line[head.len() + 1..];
+            if head.contains("::") || head.ends_with(['<', '>', '/', '=', '!']) || rest.starts_with(['=', '>']) {
+                return None;
+            }
+
+            let mut words = head.split_whitespace();
+            let name = words.next()?;
+            let operator = |word: &str| word.starts_with('`') || word.chars().all(|c| "!#$%&*+./<>?@\\^|-~:".contains(c));
+            if words.next().is_some_and(operator) {
+                return None;
+            }
+
+            Self::is_valid_identifier(name).then(|| name.to_string())
+        }
+    }
+}
+
+pub mod clj {
+    use super::{LangSpec, leading_keyword};
+    use crate::Visibility;
+
+    pub struct Clojure;
+
+    impl LangSpec for Clojure {
+        const FUNC_DEFS: &'static [&'static str] = &["(defn", "(defn-"];
+        const PARAMS_OPEN: &'static str = "[";
+        const PARAMS_CLOSE: &'static str = "]";
+        // Forms have no body opener: the signature ends with the parameter vector.
+        const END_DEF: &'static str = "";
+        const LINE_COMMENTS: &'static [&'static str] = &[";"];
+        const ACCESSORS: &'static [&'static str] = &["/"];
+        const TOP_LEVEL_CODE: bool = true;
+        const PREFIX_CALLS: bool = true;
+        const EXTRA_IDENT_CHARS: &'static str = "-?!*+<>='";
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && name.chars().all(Self::is_ident_char)
+        }
+
+        /// `(defn name [args]` and private `(defn- name`, skipping `^:private`-style metadata.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let keyword = leading_keyword(def_line, Self::FUNC_DEFS)?;
+            let name = def_line[keyword.len()..]
+                .split_whitespace()
+                .find(|word| !word.starts_with('^'))?;
+            let name: String = name.chars().take_while(|&c| Self::is_ident_char(c)).collect();
+
+            Self::is_valid_identifier(&name).then_some(name)
+        }
+
+        fn visibility(def_line: &str, _name: &str) -> Visibility {
+            if leading_keyword(def_line, &["(defn-"]).is_some() { Visibility::Private } else { Visibility::Public }
+        }
+    }
+}
+
+pub mod pl {
+    use super::{LangSpec, leading_keyword};
+    use crate::Visibility;
+
+    pub struct Perl;
+
+    impl LangSpec for Perl {
+        const FUNC_DEFS: &'static [&'static str] = &["sub"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["use", "require"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "{";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["&", "->", "::"];
+        const TOP_LEVEL_CODE: bool = true;
+        const OPTIONAL_PARAMS: bool = true;
+        const MODULE_DEFS: &'static [&'static str] = &["package"];
+        const MODULE_STATEMENTS: bool = true;
+        const MODULE_SEPARATOR: &'static str = "::";
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// `sub name {`, with an optional prototype or signature; forward declarations (`sub name;`) are skipped.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let keyword = leading_keyword(def_line, Self::FUNC_DEFS)?;
+            if def_line.trim_end().ends_with(';') {
+                return None;
+            }
+            let name: String = def_line[keyword.len()..]
+                .trim_start()
+                .chars()
+                .take_while(|&c| Self::is_ident_char(c))
+                .collect();
+
+            Self::is_valid_identifier(&name).then_some(name)
+        }
+
+        fn visibility(_def_line: &str, name: &str) -> Visibility {
+            super::underscore_visibility(name)
+        }
+    }
+}
+
+pub mod zig {
+    use super::{LangSpec, leading_keyword};
+    use crate::Visibility;
+
+    pub struct Zig;
+
+    impl LangSpec for Zig {
+        const FUNC_DEFS: &'static [&'static str] = &["fn"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "{";
+        const LINE_COMMENTS: &'static [&'static str] = &["//"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const MODIFIERS: &'static [&'static str] = &["pub", "export", "extern", "inline", "noinline"];
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// Only `pub` and `export` functions are visible outside their file.
+        fn visibility(def_line: &str, _name: &str) -> Visibility {
+            if leading_keyword(def_line, &["pub", "export"]).is_some() { Visibility::Public } else { Visibility::Private }
+        }
+    }
+}
+
+pub mod ml {
+    use super::{LangSpec, leading_keyword, strip_modifiers};
+
+    /// OCaml, and F# which shares its `let` definitions.
+    pub struct OCaml;
+
+    /// Words between `let` and the name (`let rec`, F#'s `let private`).
+    const LET_MODIFIERS: &[&str] = &["rec", "inline", "private", "internal", "public"];
+
+    impl LangSpec for OCaml {
+        const FUNC_DEFS: &'static [&'static str] = &["let"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["open"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "=";
+        // OCaml has only `(* .. *)` comments; lines opening one are skipped like F#'s `//`.
+        const LINE_COMMENTS: &'static [&'static str] = &["(*", "//"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const TOP_LEVEL_CODE: bool = true;
+        const OPTIONAL_PARAMS: bool = true;
+        const TOP_LEVEL_ONLY: bool = true;
+        const BARE_CALLS: bool = true;
+        const EXTRA_IDENT_CHARS: &'static str = "'";
+        const LOCAL_BINDINGS: &'static [&'static str] = &["let "];
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_lowercase() || c == '_')
+                && name.chars().all(Self::is_ident_char)
+        }
+
+        /// `let [rec] name args = ..`, or `let name = fun ..`/`function`; plain values
+        /// (`let x = 5`, `let x : int = 5`) and operators (`let (+) a b`) are not functions.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let keyword = leading_keyword(def_line, Self::FUNC_DEFS)?;
+            let rest = strip_modifiers(def_line[keyword.len()..].trim_start(), LET_MODIFIERS);
+            let name_len = rest.find(|c| !Self::is_ident_char(c)).unwrap_or(rest.len());
+            let name = &rest[..name_len];
+            let (params, value) = rest[name_len..].split_once('=')?;
+            let params = params.trim();
+
+            let has_params = !params.is_empty() && !params.starts_with(':');
+            let is_lambda = ["fun", "function"].iter().any(|keyword| {
+                value.trim_start().strip_prefix(keyword).is_some_and(|after| !after.starts_with(Self::is_ident_char))
+            });
+            ((has_params || is_lambda) && Self::is_valid_identifier(name)).then(|| name.to_string())
+        }
+    }
+}
+
+pub mod nim {
+    use super::{LangSpec, leading_keyword};
+    use crate::Visibility;
+
+    pub struct Nim;
+
+    impl LangSpec for Nim {
+        const FUNC_DEFS: &'static [&'static str] = &["proc", "func", "method", "iterator", "converter"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["import", "include", "from"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "=";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const TOP_LEVEL_CODE: bool = true;
+        const LOCAL_BINDINGS: &'static [&'static str] = &["var ", "let ", "const "];
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// `proc name(..): T =` and the other routine kinds, with an optional export marker
+        /// and generic parameters: `func name*[T](..) =`.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let keyword = leading_keyword(def_line, Self::FUNC_DEFS)?;
+            let rest = def_line[keyword.len()..].trim_start();
+            let name_len = rest.find(|c| !Self::is_ident_char(c)).unwrap_or(rest.len());
+            let name = &rest[..name_len];
+            let after = rest[name_len..].trim_start_matches('*').trim_start();
+
+            (after.starts_with(['(', '[']) && Self::is_valid_identifier(name)).then(|| name.to_string())
+        }
+
+        /// Exported routines carry a `*` after their name (`proc name*(`).
+        fn visibility(def_line: &str, name: &str) -> Visibility {
+            let exported = def_line
+                .find(name)
+                .is_some_and(|pos| def_line[pos + name.len()..].starts_with('*'));
+            if exported { Visibility::Public } else { Visibility::Private }
+        }
+    }
+}
+
+pub mod dart {
+    use super::{LangSpec, strip_modifiers};
+    use crate::Visibility;
+
+    pub struct Dart;
+
+    /// Words that start statements, never a return type: `return foo(x) {` is not a definition.
+    const STATEMENT_KEYWORDS: &[&str] = &[
+        "if", "else", "for", "while", "do", "switch", "case", "catch", "on", "try", "return", "await", "yield",
+        "throw", "new", "const", "assert", "super", "this",
+    ];
+
+    impl LangSpec for Dart {
+        const FUNC_DEFS: &'static [&'static str] = &[];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["import", "export"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "{";
+        const EXPRESSION_BODY: Option<&'static str> = Some("=>");
+        const LINE_COMMENTS: &'static [&'static str] = &["//"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const MODIFIERS: &'static [&'static str] = &["static", "external", "factory"];
+        const ANNOTATION_PREFIXES: &'static [&'static str] = &["@"];
+        const MODULE_DEFS: &'static [&'static str] = &["class", "abstract class", "mixin"];
+        const LOCAL_BINDINGS: &'static [&'static str] = &["var ", "final "];
+        const BRACE_SCOPES: bool = true;
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// `ReturnType name(..) {`, `name<T>(..) => expr;` and constructors (`Foo(..) {`,
+        /// `Foo.named(..) : ..`, keyed by the part after the dot). Without a return type the
+        /// parameters must close on the line and be followed by a body, so calls such as
+        /// `setState(() {` or `runApp(App());` are not taken for definitions.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let line = strip_modifiers(def_line, Self::MODIFIERS);
+            let open = super::generic_depth_find(line, '(')?;
+            let head = line[..open].trim_end();
+            let head = match head.strip_suffix('>') {
+                Some(generic) => generic[..super::generic_depth_rfind(generic)?].trim_end(),
+                None => head,
+            };
+            let name_start = head.rfind(|c: char| !Self::is_ident_char(c)).map_or(0, |pos| pos + 1);
+            let name = &head[name_start..];
+            let mut before = head[..name_start].trim_end();
+            // `Foo.named(`: a named constructor.
+            if let Some(class) = before.strip_suffix('.') {
+                if class.is_empty() || !class.chars().all(Self::is_ident_char) {
+                    return None;
+                }
+                before = "";
+            }
+            if !Self::is_valid_identifier(name) || STATEMENT_KEYWORDS.contains(&name) {
+                return None;
+            }
+
+            let mut depth = 0;
+            let close = line[open..].char_indices().find_map(|(pos, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(open + pos + 1)
+            });
+            let has_body = close.is_some_and(|close| {
+                let after = line[close..].trim_start();
+                ["{", "=>", "async", "sync*", ":"].iter().any(|opener| after.starts_with(opener))
+            });
+            let typed = !before.is_empty() && is_type(before);
+            let definition = if typed { close.is_none() || has_body } else { before.is_empty() && has_body };
+            definition.then(|| name.to_string())
+        }
+
+        fn visibility(_def_line: &str, name: &str) -> Visibility {
+            super::underscore_visibility(name)
+        }
+    }
+
+    /// A return type: one identifier, possibly prefixed (`ui.Color`), generic (`Map<String, int>`) or nullable.
+    fn is_type(text: &str) -> bool {
+        let mut depth = 0;
+        let outer: String = text
+            .chars()
+            .filter(|&c| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => return depth == 0,
+                }
+                false
+            })
+            .collect();
+        let outer = outer.trim_end_matches('?');
+        depth == 0
+            && !outer.is_empty()
+            && outer.chars().all(|c| Dart::is_ident_char(c) || c == '.')
+            && !STATEMENT_KEYWORDS.contains(&outer)
+    }
+}
+
+
+pub mod jl {
+    use super::{LangSpec, keyword_name};
+
+    pub struct Julia;
+
+    impl LangSpec for Julia {
+        const FUNC_DEFS: &'static [&'static str] = &["function"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["using", "import"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        // The block form has no opener, so the body starts right after the parameters.
+        const END_DEF: &'static str = "";
+        const LINE_COMMENTS: &'static [&'static str] = &["#"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        const TOP_LEVEL_CODE: bool = true;
+        const BLOCK_OPENERS: &'static [&'static str] =
+            &["function", "macro", "if", "for", "while", "begin", "let", "do", "try", "quote"];
+        const BLOCK_END: Option<&'static str> = Some("end");
+        const BRACKETS_HIDE_BLOCK_WORDS: bool = true;
+        const IMPORTS: &'static [&'static str] = &["import", "using"];
+        const BUILTINS: &'static [&'static str] =
+            &["Base", "Core", "Dates", "LinearAlgebra", "Printf", "Random", "Statistics", "Test"];
+        const LOCAL_BINDINGS: &'static [&'static str] = &["local ", ""];
+
+        fn is_valid_identifier(name: &str) -> bool {
+            // Mutating functions end in `!` (`push!`)
+            let stem = name.strip_suffix('!').unwrap_or(name);
+            stem.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && stem.chars().all(|c| c.is_alphanumeric() || c == '_')
+        }
+
+        /// The block form `function name(..)` .. `end` and the assignment form `name(..) = expr`
+        /// (also with `::T` or `where T` before the `=`). Methods added to another module's
+        /// function (`function Base.show(`) are keyed by their last segment.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let last_segment = |path: &str| {
+                let name = path.rsplit('.').next().unwrap_or(path);
+                Self::is_valid_identifier(name).then(|| name.to_string())
+            };
+            if let Some(path) = keyword_name(def_line, Self::FUNC_DEFS, Self::PARAMS_OPEN, |_| true) {
+                return last_segment(&path);
+            }
+
+            let open = def_line.find(Self::PARAMS_OPEN)?;
+            if def_line[..open].contains(char::is_whitespace) {
+                return None;
+            }
+            let name = last_segment(&def_line[..open])?;
+            let mut depth = 0;
+            let close = def_line[open..].char_indices().find_map(|(pos, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => return None,
+                }
+                (depth == 0).then_some(open + pos)
+            })?;
+            let rest = def_line[close + 1..].trim_start();
+            let assigns = |text: &str| text.starts_with('=') && !text.starts_with("==");
+            let annotated = (rest.starts_with("::") || rest.starts_with("where "))
+                && rest.match_indices('=').any(|(pos, _)| assigns(&rest[pos..]) && rest[..pos].ends_with(' '));
+            (assigns(rest) || annotated).then_some(name)
+        }
+    }
+}
+
+pub mod objc {
+    use super::LangSpec;
+
+    pub struct ObjectiveC;
+
+    impl LangSpec for ObjectiveC {
+        const FUNC_DEFS: &'static [&'static str] = &["-", "+"];
+        const IMPORT_KEYWORDS: &'static [&'static str] = &["#import", "#include", "@import"];
+        const PARAMS_OPEN: &'static str = "(";
+        const PARAMS_CLOSE: &'static str = ")";
+        const END_DEF: &'static str = "{";
+        const LINE_COMMENTS: &'static [&'static str] = &["//"];
+        const ACCESSORS: &'static [&'static str] = &["."];
+        // The parenthesized return type may be left out (`- init {`), defaulting to `id`.
+        const OPTIONAL_PARAMS: bool = true;
+        const TOP_LEVEL_ONLY: bool = true;
+        const BRACE_SCOPES: bool = true;
+        const MESSAGE_CALLS: bool = true;
+
+        fn is_valid_identifier(name: &str) -> bool {
+            name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
                 && name.chars().all(|c| c.is_alphanumeric() || c == '_')
         }
+
+        /// Instance (`-`) and class (`+`) methods, keyed by their selector: `- (void)run {` is
+        /// `run`, `+ (id)withName:(NSString *)name age:(int)age {` is `withName:age:`. The line
+        /// must end after the selector (a body, `;` or a comment), so expressions such as
+        /// `- (int)x;` inside a body aren't taken for definitions.
+        fn extract_name(def_line: &str) -> Option<String> {
+            let ident_end = |text: &str| text.find(|c: char| !Self::is_ident_char(c)).unwrap_or(text.len());
+            let rest = def_line.strip_prefix(['-', '+'])?.trim_start();
+            let rest = match rest.strip_prefix('(') {
+                Some(typed) => after_type(typed)?,
+                None => rest,
+            };
+            let end = ident_end(rest);
+            let mut part = &rest[..end];
+            let mut rest = rest[end..].trim_start();
+            if !Self::is_valid_identifier(part) {
+                return None;
+            }
+            if !rest.starts_with(':') {
+                return ends_selector(rest).then(|| part.to_string());
+            }
+
+            let mut selector = String::new();
+            while let Some(arg) = rest.strip_prefix(':') {
+                selector.push_str(part);
+                selector.push(':');
+                let arg = arg.trim_start();
+                let arg = match arg.strip_prefix('(') {
+                    Some(typed) => after_type(typed)?,
+                    None => arg,
+                };
+                let end = ident_end(arg);
+                if !Self::is_valid_identifier(&arg[..end]) {
+                    return None;
+                }
+                rest = arg[end..].trim_start();
+                let end = ident_end(rest);
+                if end == 0 {
+                    break;
+                }
+                part = &rest[..end];
+                rest = rest[end..].trim_start();
+                if !Self::is_valid_identifier(part) || !rest.starts_with(':') {
+                    return None;
+                }
+            }
+            // Variadic methods: `- (void)log:(NSString *)format, ... {`.
+            let rest = rest.strip_prefix(',').map_or(rest, |variadic| variadic.trim_start().trim_start_matches("...").trim_start());
+            ends_selector(rest).then_some(selector)
+        }
+    }
+
+    /// What follows the `)` closing a parenthesized type whose `(` was just stripped, such as
+    /// `(NSArray<NSString *> *)` or a block type `(void (^)(int))`.
+    fn after_type(text: &str) -> Option<&str> {
+        let mut depth = 1;
+        let close = text.char_indices().find_map(|(pos, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(pos)
+        })?;
+        Some(text[close + 1..].trim_start())
+    }
+
+    /// Whether the text after a selector can end a method header: nothing (the selector goes
+    /// on, or the body opens, on the next line), the body, a declaration's `;` or a comment.
+    fn ends_selector(rest: &str) -> bool {
+        rest.is_empty() || rest.starts_with('{') || rest == ";" || rest.starts_with("//") || rest.starts_with("/*")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::FnInfo;
+    use crate::config::Config;
+    use crate::file_info::Language;
+    use crate::parser::parse_source;
+
+    fn parse(source: &str, language: Language) -> HashMap<String, FnInfo> {
+        parse_source(source, &language, &Config::default()).unwrap()
+    }
+
+    fn callee_names(info: &FnInfo) -> Vec<&str> {
+        info.callees.iter().map(|(callee, _)| callee.as_str()).collect()
+    }
+
+    #[test]
+    fn python_async_defs_with_return_annotations() {
+        let functions = parse("async def foo() -> None:\n    await bar()\n\ndef bar() -> dict:\n    return {}\n", Language::Py);
+        assert_eq!(callee_names(&functions["foo"]), ["bar"]);
+        assert_eq!(functions["foo"].signature, "async def foo() -> None");
+        assert_eq!(functions["bar"].signature, "def bar() -> dict");
+    }
+
+    #[test]
+    fn zig_functions_returning_error_unions() {
+        let source = "const std = @import(\"std\");\n\npub fn main() !void {\n    try run();\n}\n\nfn run() !void {\n    return;\n}\n";
+        let functions = parse(source, Language::Zig);
+        assert_eq!(callee_names(&functions["main"]), ["run"]);
+        assert_eq!(functions["main"].signature, "pub fn main() !void");
+        assert_eq!(functions["main"].visibility, crate::Visibility::Public);
+        assert_eq!(functions["run"].visibility, crate::Visibility::Private);
     }
 }
@@ -1,37 +1,755 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use crate::cli::RootDetection;
 
 
+pub mod archive;
+pub mod cache;
 pub mod cli;
-pub mod lang; 
+pub mod config;
+pub mod diff;
+pub mod error;
+pub mod file_info;
+pub mod graph;
+pub mod imports;
+pub mod lang;
+pub mod log;
+pub mod lsp;
+pub mod output;
+pub mod parser;
+pub mod tui;
 
-#[derive(Debug,Clone, serde::Serialize, serde::Deserialize)]
+/// Who can call a function, from the language's own convention (`pub`, `defp`, `_name`, ..).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    /// Restricted to the crate or a parent module (`pub(crate)`, `pub(super)`).
+    Crate,
+    Private,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct FnInfo {
     pub line_at_call: usize,
     pub callees: Vec<(String, usize)>, // (callee_name, line_number)
+    /// The definition's signature collapsed onto one line, without the body opener.
+    #[serde(default)]
+    pub signature: String,
+    /// Last line (0-based) of the body; equal to `line_at_call` for one-liners.
+    #[serde(default)]
+    pub end_line: usize,
+    /// File the function was defined in; `None` for sources parsed from memory.
+    #[serde(default)]
+    pub source_file: Option<PathBuf>,
+    /// Column (0-based, in characters) of the name on the definition line.
+    #[serde(default)]
+    pub def_col: usize,
+    /// Column (0-based, in characters) of the first call to each callee, on the line `callees` records.
+    #[serde(default)]
+    pub call_cols: HashMap<String, usize>,
+    /// Called through an imported module (`utils.helper`) and defined elsewhere; `line_at_call`
+    /// is then the first call's line. Only present with `--include-external`.
+    #[serde(default)]
+    pub is_external: bool,
+    /// Takes a `self` receiver (`&self`, `&mut self`, Python's `self`).
+    #[serde(default)]
+    pub is_method: bool,
+    /// Defined in an `impl` block without a `self` receiver, like `Foo::new`.
+    #[serde(default)]
+    pub is_associated: bool,
+    /// Calls itself directly; the self-call is also among `callees` unless `--no-self-edges` removed it.
+    #[serde(default)]
+    pub is_recursive: bool,
+    /// Callees only mentioned by name, not called (`register(callback)`); `--include-references`.
+    #[serde(default)]
+    pub references: HashSet<String>,
+    /// Decorator that changes the function's kind (`property`, `classmethod`, `staticmethod`, ..).
+    #[serde(default)]
+    pub decorator: Option<String>,
+    /// Declared without a body (`fn area(&self) -> f64;` in a trait), so it has no callees.
+    #[serde(default)]
+    pub is_declaration: bool,
+    /// Public unless the language marks it otherwise; `--public-only` keeps just the public ones.
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Names the function had before `--normalize-names` changed or merged them.
+    #[serde(default)]
+    pub raw_names: Vec<String>,
+}
+
+impl FnInfo {
+    /// The same function with `callees` (and `raw_names`) sorted. Their order follows the source
+    /// and the merge order of files, so compare normalized values when only the calls matter.
+    pub fn normalized(mut self) -> Self {
+        self.callees.sort();
+        self.raw_names.sort();
+        self
+    }
+}
+
+/// How human-readable output locates a definition: `line 42`, or `path:42` in merged graphs
+/// where a bare line number would be ambiguous.
+#[derive(Debug, Clone, Default)]
+pub enum Location {
+    /// `line 42`.
+    #[default]
+    Line,
+    /// `sub/foo.py:42`, with paths relative to this directory.
+    RelativeTo(PathBuf),
+    /// `/work/src/sub/foo.py:42`.
+    Absolute,
+}
+
+impl Location {
+    /// Where `info` is defined; functions with no source file (external ones) keep `line N`.
+    pub fn of(&self, info: &FnInfo) -> String {
+        let line = info.line_at_call + 1;
+        match (self, &info.source_file) {
+            (Location::RelativeTo(root), Some(path)) => {
+                format!("{}:{}", path.strip_prefix(root).unwrap_or(path).display(), line)
+            }
+            (Location::Absolute, Some(path)) => {
+                format!("{}:{}", std::path::absolute(path).unwrap_or_else(|_| path.clone()).display(), line)
+            }
+            _ => format!("line {}", line),
+        }
+    }
+}
+
+/// Rendering switches for [`print_tree`].
+#[derive(Debug, Clone, Default)]
+pub struct TreeOptions {
+    /// Append each function's signature (shown at `InfoLevel::L3`).
+    pub show_signatures: bool,
+    /// Tag methods `[method]` and associated functions `[assoc]` (shown from `InfoLevel::L2`).
+    pub show_kinds: bool,
+    /// Functions tagged `[changed]` (from `--since`).
+    pub highlight: HashSet<String>,
+    /// How each function's definition is located.
+    pub location: Location,
+    /// Show a callee's file only where it differs from its caller's (`--callee-files`); callees
+    /// in the same file get a bare `line N`.
+    pub callee_files: bool,
+    /// Lines of source shown before and after each call site under the callee (`--context-lines`).
+    pub context_lines: Option<usize>,
+    /// Source lines by file, for `context_lines`; calls in files missing here get no context.
+    pub sources: HashMap<PathBuf, Vec<String>>,
 }
 
 pub fn find_roots(hm: &HashMap<String, FnInfo>) -> Vec<String> {
     let all_fns: HashSet<&String> = hm.keys().collect();
     let mut called_fns = HashSet::new();
 
-    for info in hm.values() {
+    // Recursion alone doesn't make a function called.
+    for (name, info) in hm {
         for (callee, _) in &info.callees {
-            called_fns.insert(callee);
+            if callee != name {
+                called_fns.insert(callee);
+            }
         }
     }
 
-    all_fns
+    let mut roots: Vec<String> = all_fns
         .difference(&called_fns)
         .map(|s| (*s).clone())
+        .collect();
+    roots.sort();
+    roots
+}
+
+/// Iterates over every `(caller, callee, call_line)` edge, ordered by caller name.
+pub fn edges(hm: &HashMap<String, FnInfo>) -> impl Iterator<Item = (&str, &str, usize)> {
+    let mut callers: Vec<&String> = hm.keys().collect();
+    callers.sort();
+
+    callers.into_iter().flat_map(move |caller| {
+        hm[caller]
+            .callees
+            .iter()
+            .map(move |(callee, line)| (caller.as_str(), callee.as_str(), *line))
+    })
+}
+
+/// Maps every function to the distinct functions that call it (the inverse of `callees`).
+pub fn callers(hm: &HashMap<String, FnInfo>) -> HashMap<&str, Vec<&str>> {
+    let mut inverted: HashMap<&str, Vec<&str>> = hm.keys().map(|name| (name.as_str(), Vec::new())).collect();
+
+    for (caller, callee, _) in edges(hm) {
+        if let Some(list) = inverted.get_mut(callee)
+            && !list.contains(&caller)
+        {
+            list.push(caller);
+        }
+    }
+
+    inverted
+}
+
+/// Every function reachable from `starts` by following callees (the starts included).
+pub fn reachable_from(hm: &HashMap<String, FnInfo>, starts: impl IntoIterator<Item = String>) -> HashSet<String> {
+    let mut reached = HashSet::new();
+    let mut stack: Vec<String> = starts.into_iter().collect();
+
+    while let Some(name) = stack.pop() {
+        if let Some(info) = hm.get(&name)
+            && reached.insert(name)
+        {
+            stack.extend(info.callees.iter().map(|(callee, _)| callee.clone()));
+        }
+    }
+
+    reached
+}
+
+/// `centers` plus every function that directly calls or is called by one of them.
+pub fn neighborhood(hm: &HashMap<String, FnInfo>, centers: &HashSet<String>) -> HashSet<String> {
+    let mut around = centers.clone();
+    for (caller, callee, _) in edges(hm) {
+        if centers.contains(caller) {
+            around.insert(callee.to_string());
+        }
+        if centers.contains(callee) {
+            around.insert(caller.to_string());
+        }
+    }
+    around.retain(|name| hm.contains_key(name));
+    around
+}
+
+/// Canonical form of a function name for `--normalize-names`: generic arguments and lifetimes go
+/// (`Wrapper<'a, T>::get` becomes `Wrapper::get`), and a trait-qualified receiver keeps just its
+/// type (`<&Sq as Shape>::area` becomes `Sq::area`).
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(open) = rest.find('<') {
+        normalized.push_str(&rest[..open]);
+        // The matching `>`, past nested generics; the `>` of an arrow (`Fn() -> T`) closes nothing.
+        let mut depth = 0;
+        let close = rest[open..].char_indices().find_map(|(pos, c)| {
+            match c {
+                '<' => depth += 1,
+                '>' if !rest[..open + pos].ends_with('-') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(open + pos);
+                    }
+                }
+                _ => {}
+            }
+            None
+        });
+        let Some(close) = close else {
+            normalized.push_str(&rest[open..]);
+            return normalized;
+        };
+        if (normalized.is_empty() || normalized.ends_with("::")) && rest[close + 1..].starts_with("::") {
+            let inner = &rest[open + 1..close];
+            let ty = inner.split(" as ").next().unwrap_or(inner).trim_start_matches('&').trim_start();
+            let ty = if ty.starts_with('\'') { ty.split_once(' ').map_or("", |(_, ty)| ty) } else { ty };
+            let ty = ty.trim_start_matches("mut ").trim_start_matches("dyn ").trim();
+            normalized.push_str(&normalize_name(ty));
+        }
+        rest = &rest[close + 1..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// Renames every function to its [`normalize_name`], as [`rename_functions`] does.
+pub fn normalize_names(hm: HashMap<String, FnInfo>) -> HashMap<String, FnInfo> {
+    rename_functions(hm, normalize_name)
+}
+
+/// Renames every function, and every call to it, to `rename(name)`. Functions that meet under
+/// one name are merged: callees combined, the first definition's (by raw name) location kept.
+/// Each renamed function lists its original names in `raw_names`.
+pub fn rename_functions(hm: HashMap<String, FnInfo>, rename: impl Fn(&str) -> String) -> HashMap<String, FnInfo> {
+    let mut entries: Vec<(String, FnInfo)> = hm.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut renamed: HashMap<String, FnInfo> = HashMap::new();
+    for (raw, mut info) in entries {
+        let mut callees: Vec<(String, usize)> = Vec::new();
+        for (callee, line) in info.callees.drain(..) {
+            let callee = rename(&callee);
+            if !callees.iter().any(|(name, _)| *name == callee) {
+                callees.push((callee, line));
+            }
+        }
+        info.callees = callees;
+        info.call_cols = info.call_cols.drain().map(|(callee, col)| (rename(&callee), col)).collect();
+        info.references = info.references.drain().map(|callee| rename(&callee)).collect();
+
+        let name = rename(&raw);
+        if name != raw {
+            info.raw_names.push(raw);
+        }
+        match renamed.get_mut(&name) {
+            Some(existing) => {
+                // A call from either side beats a bare reference.
+                for (callee, line) in info.callees {
+                    let reference = info.references.contains(&callee);
+                    if existing.callees.iter().any(|(known, _)| *known == callee) {
+                        if !reference {
+                            existing.references.remove(&callee);
+                        }
+                    } else {
+                        if reference {
+                            existing.references.insert(callee.clone());
+                        }
+                        existing.callees.push((callee, line));
+                    }
+                }
+                for (callee, col) in info.call_cols {
+                    existing.call_cols.entry(callee).or_insert(col);
+                }
+                existing.is_recursive |= info.is_recursive;
+                existing.raw_names.extend(info.raw_names);
+            }
+            None => {
+                renamed.insert(name, info);
+            }
+        }
+    }
+    renamed
+}
+
+/// Removes leaf functions (those calling nothing but themselves) and the edges into them,
+/// `levels` times over: each pass can expose the callers of the previous one's leaves.
+pub fn prune_leaves(hm: &mut HashMap<String, FnInfo>, levels: usize) {
+    for _ in 0..levels {
+        let leaves: HashSet<String> = hm
+            .iter()
+            .filter(|(name, info)| info.callees.iter().all(|(callee, _)| callee == *name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        if leaves.is_empty() {
+            break;
+        }
+        hm.retain(|name, _| !leaves.contains(name));
+        for info in hm.values_mut() {
+            info.callees.retain(|(callee, _)| !leaves.contains(callee));
+            info.call_cols.retain(|callee, _| !leaves.contains(callee));
+            info.references.retain(|callee| !leaves.contains(callee));
+        }
+    }
+}
+
+/// Removes the functions called from fewer than `min_in` other functions or calling fewer than
+/// `min_out`, and the edges into them. Both counts are taken on the graph as given, in one pass.
+pub fn filter_fan(hm: &mut HashMap<String, FnInfo>, min_in: usize, min_out: usize) {
+    let dropped: HashSet<String> = fan_counts(hm)
+        .into_iter()
+        .filter(|(_, (fan_in, fan_out))| *fan_in < min_in || *fan_out < min_out)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    hm.retain(|name, _| !dropped.contains(name));
+    for info in hm.values_mut() {
+        info.callees.retain(|(callee, _)| !dropped.contains(callee));
+        info.call_cols.retain(|callee, _| !dropped.contains(callee));
+        info.references.retain(|callee| !dropped.contains(callee));
+    }
+}
+
+/// Fan-in and fan-out of every function, as `--min-fan-in`/`--min-fan-out` and the
+/// `--top-callers`/`--top-callees` rankings count them: distinct callers and callees, leaving out
+/// a function's calls to itself, edges to `[external]` functions and bare references.
+pub fn fan_counts(hm: &HashMap<String, FnInfo>) -> HashMap<&str, (usize, usize)> {
+    let mut counts: HashMap<&str, (usize, usize)> = hm.keys().map(|name| (name.as_str(), (0, 0))).collect();
+    for (name, info) in hm {
+        let callees: HashSet<&str> = info
+            .callees
+            .iter()
+            .map(|(callee, _)| callee.as_str())
+            .filter(|callee| {
+                *callee != name && !info.references.contains(*callee) && hm.get(*callee).is_some_and(|callee| !callee.is_external)
+            })
+            .collect();
+        counts.entry(name).or_default().1 = callees.len();
+        for callee in callees {
+            counts.entry(callee).or_default().0 += 1;
+        }
+    }
+    counts
+}
+
+/// Roots under a `--root-detection` strategy, in tree order. `named` keeps the `names` that are
+/// defined, in the order given; `all` leaves no function unreachable.
+pub fn detect_roots(hm: &HashMap<String, FnInfo>, detection: RootDetection, names: &[String]) -> Vec<String> {
+    match detection {
+        RootDetection::Uncalled => find_roots(hm),
+        RootDetection::Named => named_roots(hm, names),
+        // Functions left over after the uncalled roots' trees (cycles nobody enters) each
+        // start a tree of their own, in name order.
+        RootDetection::All => {
+            let mut roots = find_roots(hm);
+            let mut reached = reachable_from(hm, roots.iter().cloned());
+            for name in not_reached(hm, &reached) {
+                if !reached.contains(&name) {
+                    reached.extend(reachable_from(hm, [name.clone()]));
+                    roots.push(name);
+                }
+            }
+            roots
+        }
+        // The named entrypoints and uncalled public functions, then public functions only
+        // internal code calls, in name order.
+        RootDetection::Public => {
+            let public = |name: &String| hm[name].visibility == Visibility::Public && !hm[name].is_external;
+            let mut roots = named_roots(hm, names);
+            let uncalled: Vec<String> = find_roots(hm).into_iter().filter(|name| public(name) && !roots.contains(name)).collect();
+            roots.extend(uncalled);
+            let mut reached = reachable_from(hm, roots.iter().cloned());
+            for name in not_reached(hm, &reached) {
+                if public(&name) && !reached.contains(&name) {
+                    reached.extend(reachable_from(hm, [name.clone()]));
+                    roots.push(name);
+                }
+            }
+            roots
+        }
+    }
+}
+
+/// Dead code: functions nothing calls (calls to themselves aside) that aren't among the
+/// `entrypoints` or kept by `allowed`, sorted by name. Cycle members call each other and
+/// external nodes aren't defined here, so neither counts.
+pub fn find_orphans(hm: &HashMap<String, FnInfo>, entrypoints: &[String], allowed: impl Fn(&str) -> bool) -> Vec<String> {
+    find_roots(hm)
+        .into_iter()
+        .filter(|name| !hm[name].is_external && !entrypoints.iter().any(|entry| entry == block_name(name)) && !allowed(name))
         .collect()
 }
 
+/// The functions named `names`, in that order. Top-level code merged from several files
+/// (`a.py::<module>`, `b.py::<module>`) answers to its unqualified name.
+fn named_roots(hm: &HashMap<String, FnInfo>, names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .flat_map(|name| {
+            let mut found: Vec<&String> = hm.keys().filter(|key| block_name(key) == name).collect();
+            found.sort();
+            found.into_iter().cloned()
+        })
+        .collect()
+}
+
+/// `name` without the file qualifier a synthetic top-level block (`a.py::<module>`) gets when
+/// several files have one.
+fn block_name(name: &str) -> &str {
+    match name.rsplit_once("::") {
+        Some((_, block)) if block == parser::MODULE_BLOCK || block == parser::MAIN_BLOCK => block,
+        _ => name,
+    }
+}
+
+/// Pseudonyms for `--anonymize`: `fn_001`, `fn_002`, .. given to every function and callee in
+/// order of their names, so the same graph always gets the same ones. Numbers are padded to one
+/// width, which keeps the pseudonyms in the same order as the names.
+pub fn pseudonyms(hm: &HashMap<String, FnInfo>) -> BTreeMap<String, String> {
+    let names: BTreeSet<&str> = hm
+        .iter()
+        .flat_map(|(name, info)| std::iter::once(name).chain(info.callees.iter().map(|(callee, _)| callee)))
+        .map(String::as_str)
+        .collect();
+    let width = names.len().to_string().len().max(3);
+    names.into_iter().enumerate().map(|(i, name)| (name.to_string(), format!("fn_{:0width$}", i + 1))).collect()
+}
+
+/// Renames every function to its entry in `pseudonyms` and clears what would still give the
+/// names away: signatures, and the raw names kept by renames.
+pub fn anonymize(hm: HashMap<String, FnInfo>, pseudonyms: &BTreeMap<String, String>) -> HashMap<String, FnInfo> {
+    let mut anonymized = rename_functions(hm, |name| pseudonyms.get(name).cloned().unwrap_or_else(|| name.to_string()));
+    for info in anonymized.values_mut() {
+        info.signature.clear();
+        info.raw_names.clear();
+    }
+    anonymized
+}
+
+/// Thin wrappers: functions that call something, but only `[external]` functions, sorted by
+/// name. Without external edges (`--include-external`) there are none.
+pub fn find_wrappers(hm: &HashMap<String, FnInfo>) -> Vec<String> {
+    let mut wrappers: Vec<String> = hm
+        .iter()
+        .filter(|(_, info)| {
+            !info.is_external
+                && !info.callees.is_empty()
+                && info.callees.iter().all(|(callee, _)| hm.get(callee).is_some_and(|callee| callee.is_external))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    wrappers.sort();
+    wrappers
+}
+
+/// Functions that no root reaches: what is left once every tree under `find_roots` is walked.
+pub fn unreachable(hm: &HashMap<String, FnInfo>) -> Vec<String> {
+    not_reached(hm, &reachable_from(hm, find_roots(hm)))
+}
+
+/// The functions outside `reached`, sorted by name.
+pub fn not_reached(hm: &HashMap<String, FnInfo>, reached: &HashSet<String>) -> Vec<String> {
+    let mut remaining: Vec<String> = hm.keys().filter(|k| !reached.contains(*k)).cloned().collect();
+    remaining.sort();
+    remaining
+}
+
+/// Rough number of bytes the parsed map occupies: table slots plus the heap data of keys, callees, columns, signatures and paths.
+pub fn estimated_memory(hm: &HashMap<String, FnInfo>) -> usize {
+    // Each hash table slot holds one entry and a control byte.
+    let table = hm.capacity() * (std::mem::size_of::<(String, FnInfo)>() + 1);
+    let heap: usize = hm
+        .iter()
+        .map(|(name, info)| {
+            name.len()
+                + info.signature.len()
+                + info.decorator.as_ref().map_or(0, String::len)
+                + info.raw_names.iter().map(|raw| std::mem::size_of::<String>() + raw.len()).sum::<usize>()
+                + info.source_file.as_ref().map_or(0, |path| path.as_os_str().len())
+                + info.call_cols.keys().map(|callee| std::mem::size_of::<(String, usize)>() + callee.len()).sum::<usize>()
+                + info.references.iter().map(|callee| std::mem::size_of::<String>() + callee.len()).sum::<usize>()
+                + info
+                    .callees
+                    .iter()
+                    .map(|(callee, _)| std::mem::size_of::<(String, usize)>() + callee.len())
+                    .sum::<usize>()
+        })
+        .sum();
+    table + heap
+}
+
+/// Finds call cycles as strongly connected components (Tarjan's algorithm).
+///
+/// A component counts as a cycle if it has more than one member or its single member calls itself.
+/// Members are sorted within each cycle, and cycles are sorted by their first member.
+pub fn find_cycles(hm: &HashMap<String, FnInfo>) -> Vec<Vec<String>> {
+    struct Tarjan<'a> {
+        hm: &'a HashMap<String, FnInfo>,
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        on_stack: HashSet<&'a str>,
+        stack: Vec<&'a str>,
+        cycles: Vec<Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, name: &'a str) {
+            let idx = self.index.len();
+            self.index.insert(name, idx);
+            self.low.insert(name, idx);
+            self.stack.push(name);
+            self.on_stack.insert(name);
+
+            for (callee, _) in &self.hm[name].callees {
+                let callee = callee.as_str();
+                if !self.hm.contains_key(callee) {
+                    continue;
+                }
+                if !self.index.contains_key(callee) {
+                    self.visit(callee);
+                    let low = self.low[name].min(self.low[callee]);
+                    self.low.insert(name, low);
+                } else if self.on_stack.contains(callee) {
+                    let low = self.low[name].min(self.index[callee]);
+                    self.low.insert(name, low);
+                }
+            }
+
+            if self.low[name] == self.index[name] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member.to_string());
+                    if member == name {
+                        break;
+                    }
+                }
+                let self_loop = self.hm[name].callees.iter().any(|(callee, _)| callee == name);
+                if component.len() > 1 || self_loop {
+                    component.sort();
+                    self.cycles.push(component);
+                }
+            }
+        }
+    }
+
+    let mut names: Vec<&str> = hm.keys().map(String::as_str).collect();
+    names.sort();
+
+    let mut tarjan = Tarjan {
+        hm,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        on_stack: HashSet::new(),
+        stack: Vec::new(),
+        cycles: Vec::new(),
+    };
+    for name in names {
+        if !tarjan.index.contains_key(name) {
+            tarjan.visit(name);
+        }
+    }
+
+    tarjan.cycles.sort();
+    tarjan.cycles
+}
+
+/// The cycles `--fail-on-cycles` fails on: those through more than one function. A function
+/// calling only itself is recursion, marked by `is_recursive`, not a circular dependency.
+pub fn dependency_cycles(hm: &HashMap<String, FnInfo>) -> Vec<Vec<String>> {
+    find_cycles(hm).into_iter().filter(|cycle| cycle.len() > 1).collect()
+}
+
+/// Architecture figures over the whole graph, for `--report coupling`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coupling {
+    pub functions: usize,
+    /// Distinct caller -> callee pairs between defined functions, self-calls left out.
+    pub edges: usize,
+    /// `edges` over the `n * (n - 1)` possible ones; 0 below two functions.
+    pub density: f64,
+    /// Mean length of the shortest call path over every pair of a function and something it
+    /// reaches; `None` when nothing calls anything.
+    pub average_path_length: Option<f64>,
+    /// Functions that are members of a cycle ([`find_cycles`]).
+    pub in_cycles: usize,
+    /// Functions whose removal splits the graph into more pieces, with calls taken in either
+    /// direction; sorted by name.
+    pub articulation_points: Vec<String>,
+}
+
+/// Measures how tightly the functions in `hm` are coupled (see [`Coupling`]).
+pub fn coupling(hm: &HashMap<String, FnInfo>) -> Coupling {
+    let mut names: Vec<&str> = hm.keys().map(String::as_str).collect();
+    names.sort();
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, name)| (*name, i)).collect();
+
+    let mut calls: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+    for (caller, callee, _) in edges(hm) {
+        let (from, Some(&to)) = (index[caller], index.get(callee)) else {
+            continue;
+        };
+        if from != to && !calls[from].contains(&to) {
+            calls[from].push(to);
+            if !neighbours[from].contains(&to) {
+                neighbours[from].push(to);
+                neighbours[to].push(from);
+            }
+        }
+    }
+
+    let n = names.len();
+    let edge_count: usize = calls.iter().map(Vec::len).sum();
+    let density = if n < 2 { 0.0 } else { edge_count as f64 / (n * (n - 1)) as f64 };
+
+    // Breadth-first from every function gives each shortest path it starts.
+    let (mut total, mut pairs) = (0usize, 0usize);
+    let mut distance = vec![usize::MAX; n];
+    for start in 0..n {
+        distance.fill(usize::MAX);
+        distance[start] = 0;
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for &next in &calls[node] {
+                if distance[next] == usize::MAX {
+                    distance[next] = distance[node] + 1;
+                    total += distance[next];
+                    pairs += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    struct CutVertices<'a> {
+        neighbours: &'a [Vec<usize>],
+        discovered: Vec<Option<usize>>,
+        low: Vec<usize>,
+        cut: Vec<bool>,
+        visited: usize,
+    }
+
+    impl CutVertices<'_> {
+        fn visit(&mut self, node: usize, parent: Option<usize>) {
+            let order = self.visited;
+            self.visited += 1;
+            self.discovered[node] = Some(order);
+            self.low[node] = order;
+            let mut children = 0;
+
+            for &next in &self.neighbours[node] {
+                match self.discovered[next] {
+                    Some(seen) => {
+                        if Some(next) != parent {
+                            self.low[node] = self.low[node].min(seen);
+                        }
+                    }
+                    None => {
+                        children += 1;
+                        self.visit(next, Some(node));
+                        self.low[node] = self.low[node].min(self.low[next]);
+                        if parent.is_some() && self.low[next] >= order {
+                            self.cut[node] = true;
+                        }
+                    }
+                }
+            }
+
+            // A DFS root splits the graph only if it has several subtrees.
+            if parent.is_none() && children > 1 {
+                self.cut[node] = true;
+            }
+        }
+    }
+
+    let mut cut_vertices = CutVertices {
+        neighbours: &neighbours,
+        discovered: vec![None; n],
+        low: vec![0; n],
+        cut: vec![false; n],
+        visited: 0,
+    };
+    for node in 0..n {
+        if cut_vertices.discovered[node].is_none() {
+            cut_vertices.visit(node, None);
+        }
+    }
+
+    Coupling {
+        functions: n,
+        edges: edge_count,
+        density,
+        average_path_length: (pairs > 0).then(|| total as f64 / pairs as f64),
+        in_cycles: find_cycles(hm).iter().map(Vec::len).sum(),
+        articulation_points: (0..n).filter(|&node| cut_vertices.cut[node]).map(|node| names[node].to_string()).collect(),
+    }
+}
+
 pub fn print_tree(
     name: &str,
     hm: &HashMap<String, FnInfo>,
     prefix: String,
     is_last: bool,
     visited: &mut HashSet<String>,
+    options: &TreeOptions,
+) {
+    print_subtree(name, hm, prefix, is_last, visited, options, None);
+}
+
+/// [`print_tree`] for a callee called at `call` (0-based line in the caller's file).
+fn print_subtree(
+    name: &str,
+    hm: &HashMap<String, FnInfo>,
+    prefix: String,
+    is_last: bool,
+    visited: &mut HashSet<String>,
+    options: &TreeOptions,
+    call: Option<(&PathBuf, usize)>,
 ) {
     if !visited.insert(name.to_string()) {
         return;
@@ -40,7 +758,27 @@ pub fn print_tree(
     let connector = if is_last { "└── " } else { "├── " };
     let fn_info = &hm[name];
 
-    println!("{}{}{} (line {})", prefix, connector, name, fn_info.line_at_call);
+    let kind = match (options.show_kinds, &fn_info.decorator, fn_info.is_method, fn_info.is_associated) {
+        (true, Some(decorator), _, _) => format!(" [{}]", decorator),
+        (true, None, true, _) => " [method]".to_string(),
+        (true, None, _, true) => " [assoc]".to_string(),
+        _ => String::new(),
+    };
+    let kind = if options.show_kinds && fn_info.is_declaration { format!("{} [declaration]", kind) } else { kind };
+    let recursive = if fn_info.is_recursive { " [recursive]" } else { "" };
+    let changed = if options.highlight.contains(name) { " [changed]" } else { "" };
+    let tag = format!("{}{}{}", kind, recursive, changed);
+    let location = match call {
+        Some((file, _)) if options.callee_files && fn_info.source_file.as_ref() == Some(file) => Location::Line.of(fn_info),
+        _ => options.location.of(fn_info),
+    };
+    if fn_info.is_external {
+        println!("{}{}{} [external]{}", prefix, connector, name, tag);
+    } else if options.show_signatures && !fn_info.signature.is_empty() {
+        println!("{}{}{} ({}){}: {}", prefix, connector, name, location, tag, fn_info.signature);
+    } else {
+        println!("{}{}{} ({}){}", prefix, connector, name, location, tag);
+    }
 
     let new_prefix = if is_last {
         format!("{}    ", prefix)
@@ -48,13 +786,59 @@ pub fn print_tree(
         format!("{}│   ", prefix)
     };
 
+    if let Some(n) = options.context_lines
+        && let Some((file, line)) = call
+        && let Some(source) = options.sources.get(file)
+    {
+        let last = (line + n).min(source.len().saturating_sub(1));
+        let width = (last + 1).to_string().len();
+        for (k, text) in source.iter().enumerate().take(last + 1).skip(line.saturating_sub(n)) {
+            let marker = if k == line { '>' } else { ' ' };
+            println!("{}  {} {:>width$} | {}", new_prefix, marker, k + 1, text);
+        }
+    }
+
     let callees = &fn_info.callees;
     let len = callees.len();
-    for (i, (callee, _)) in callees.iter().enumerate() {
+    for (i, (callee, line)) in callees.iter().enumerate() {
         let is_last_callee = i == len - 1;
-        print_tree(callee, hm, new_prefix.clone(), is_last_callee, visited);
+        let call = fn_info.source_file.as_ref().map(|file| (file, *line));
+        print_subtree(callee, hm, new_prefix.clone(), is_last_callee, visited, options, call);
     }
 }
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph of plain functions with the given callees.
+    fn graph(calls: &[(&str, &[&str])]) -> HashMap<String, FnInfo> {
+        calls
+            .iter()
+            .map(|(name, callees)| {
+                let info = FnInfo {
+                    callees: callees.iter().map(|callee| (callee.to_string(), 0)).collect(),
+                    is_recursive: callees.contains(name),
+                    ..FnInfo::default()
+                };
+                (name.to_string(), info)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recursion_alone_is_not_a_dependency_cycle() {
+        let hm = graph(&[("main", &["factorial"]), ("factorial", &["factorial"])]);
+        assert_eq!(find_cycles(&hm), vec![vec!["factorial".to_string()]]);
+        assert!(dependency_cycles(&hm).is_empty());
+    }
+
+    #[test]
+    fn mutual_recursion_is_a_dependency_cycle() {
+        let hm = graph(&[("main", &["a"]), ("a", &["b", "a"]), ("b", &["a"])]);
+        assert_eq!(dependency_cycles(&hm), vec![vec!["a".to_string(), "b".to_string()]]);
+    }
+}
@@ -0,0 +1,38 @@
+//! Verbosity-gated diagnostics on stderr, set once from `-v`/`-vv`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// `-v`: cache decisions and skipped definitions.
+pub const INFO: u8 = 1;
+/// `-vv`: what the parser decided for every line and each edge it recorded.
+pub const DEBUG: u8 = 2;
+
+pub fn set_level(level: u8) {
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+pub fn enabled(level: u8) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level
+}
+
+/// Prints to stderr when the verbosity is at least `-v`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::INFO) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Prints to stderr when the verbosity is at least `-vv`.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::DEBUG) {
+            eprintln!($($arg)*);
+        }
+    };
+}
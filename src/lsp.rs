@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use serde_json::{Value, json};
+use crate::config::Config;
+use crate::file_info::FileInfo;
+use crate::graph::CallGraph;
+use crate::parser::parse_file;
+use crate::{FnInfo, output};
+
+/// `SymbolKind.Function` in the LSP specification.
+const SYMBOL_FUNCTION: u32 = 12;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Serves call hierarchies over LSP-framed JSON-RPC on stdin/stdout until `exit` or end of input.
+///
+/// Handles `initialize`, `shutdown`, `textDocument/prepareCallHierarchy`,
+/// `callHierarchy/incomingCalls`, `callHierarchy/outgoingCalls` and `pars/callGraph`; each
+/// request re-parses the file its URI names, so edits on disk are picked up.
+pub fn run(config: &Config) -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut out = io::stdout().lock();
+
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        // Notifications (`initialized`, `textDocument/didOpen`, ..) carry no id and get no reply.
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+
+        let response = match handle(method, &message["params"], config) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, text)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": text } }),
+        };
+        write_message(&mut out, &response)?;
+    }
+    Ok(())
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message without a Content-Length header"));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+fn write_message(out: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
+
+type Reply = Result<Value, (i64, String)>;
+
+fn handle(method: &str, params: &Value, config: &Config) -> Reply {
+    match method {
+        "initialize" => Ok(json!({
+            "capabilities": { "callHierarchyProvider": true },
+            "serverInfo": { "name": "pars", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "shutdown" => Ok(Value::Null),
+        "textDocument/prepareCallHierarchy" => {
+            let uri = str_param(&params["textDocument"]["uri"], "textDocument.uri")?;
+            let line = params["position"]["line"].as_u64().ok_or((INVALID_PARAMS, "missing position.line".to_string()))?;
+            let functions = parse_uri(uri, config)?;
+
+            // The innermost function whose body spans the line.
+            let found = functions
+                .iter()
+                .filter(|(_, info)| (info.line_at_call..=info.end_line).contains(&(line as usize)))
+                .max_by_key(|(name, info)| (info.line_at_call, std::cmp::Reverse(*name)));
+            Ok(match found {
+                Some((name, info)) => json!([item(uri, name, info)]),
+                None => Value::Null,
+            })
+        }
+        "callHierarchy/incomingCalls" => {
+            let (uri, key) = item_params(params)?;
+            let functions = parse_uri(uri, config)?;
+            let graph = CallGraph::new(&functions);
+            let calls: Vec<Value> = graph
+                .callers(key)
+                .iter()
+                .map(|caller| {
+                    let info = &functions[*caller];
+                    json!({ "from": item(uri, caller, info), "fromRanges": call_ranges(info, key) })
+                })
+                .collect();
+            Ok(json!(calls))
+        }
+        "callHierarchy/outgoingCalls" => {
+            let (uri, key) = item_params(params)?;
+            let functions = parse_uri(uri, config)?;
+            let graph = CallGraph::new(&functions);
+            let Some(info) = graph.get(key) else {
+                return Ok(json!([]));
+            };
+            let calls: Vec<Value> = graph
+                .callees(key)
+                .into_iter()
+                .filter_map(|callee| functions.get(callee).map(|target| (callee, target)))
+                .map(|(callee, target)| json!({ "to": item(uri, callee, target), "fromRanges": call_ranges(info, callee) }))
+                .collect();
+            Ok(json!(calls))
+        }
+        "pars/callGraph" => {
+            let uri = str_param(&params["textDocument"]["uri"], "textDocument.uri")?;
+            let functions = parse_uri(uri, config)?;
+            let mut document = Vec::new();
+            output::write_json(&mut document, &functions, false).map_err(|e| (INTERNAL_ERROR, e.to_string()))?;
+            serde_json::from_slice(&document).map_err(|e| (INTERNAL_ERROR, e.to_string()))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("unsupported method: {}", method))),
+    }
+}
+
+fn str_param<'a>(value: &'a Value, name: &str) -> Result<&'a str, (i64, String)> {
+    value.as_str().ok_or_else(|| (INVALID_PARAMS, format!("missing {}", name)))
+}
+
+/// The URI and function key of the `item` a hierarchy request is about.
+fn item_params(params: &Value) -> Result<(&str, &str), (i64, String)> {
+    let item = &params["item"];
+    Ok((str_param(&item["uri"], "item.uri")?, str_param(&item["data"]["key"], "item.data.key")?))
+}
+
+fn parse_uri(uri: &str, config: &Config) -> Result<HashMap<String, FnInfo>, (i64, String)> {
+    let path = uri_to_path(uri).ok_or_else(|| (INVALID_PARAMS, format!("not a file URI: {}", uri)))?;
+    let file_info = FileInfo::from_path(&path).map_err(|e| (INTERNAL_ERROR, e.to_string()))?;
+    parse_file(&file_info, config).map_err(|e| (INTERNAL_ERROR, e.to_string()))
+}
+
+/// Decodes a `file://` URI, including `%XX` escapes, into a path.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%'
+            && let Some(byte) = encoded.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+        {
+            bytes.push(byte);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Length of the name as written in source: the last segment of a qualified key (`Type::name`, `Class.name`).
+fn name_len(key: &str) -> usize {
+    key.rsplit([':', '.']).next().unwrap_or(key).chars().count()
+}
+
+fn range(line: usize, start: usize, end_line: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": end_line, "character": end },
+    })
+}
+
+/// A `CallHierarchyItem`; `data.key` carries the graph key back in follow-up requests.
+fn item(uri: &str, key: &str, info: &FnInfo) -> Value {
+    json!({
+        "name": key,
+        "kind": SYMBOL_FUNCTION,
+        "uri": uri,
+        "detail": info.signature,
+        "range": range(info.line_at_call, 0, info.end_line + 1, 0),
+        "selectionRange": range(info.line_at_call, info.def_col, info.line_at_call, info.def_col + name_len(key)),
+        "data": { "key": key },
+    })
+}
+
+/// Where `caller` calls `callee`: the first call only, the one `call_cols` has a column for.
+fn call_ranges(caller: &FnInfo, callee: &str) -> Value {
+    let ranges: Vec<Value> = caller
+        .callees
+        .iter()
+        .find(|(name, _)| name == callee)
+        .map(|(_, line)| {
+            let col = caller.call_cols.get(callee).copied().unwrap_or_default();
+            range(*line, col, *line, col + name_len(callee))
+        })
+        .into_iter()
+        .collect();
+    json!(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// `tests/fixtures/lsp/call hierarchy.py`, with the space escaped as editors send it.
+    fn fixture_uri() -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lsp/call hierarchy.py");
+        format!("file://{}", path.display().to_string().replace(' ', "%20"))
+    }
+
+    fn request(method: &str, params: Value) -> Value {
+        handle(method, &params, &Config::default()).unwrap()
+    }
+
+    #[test]
+    fn messages_are_framed_by_content_length() {
+        let first = r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#;
+        let second = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let stream = format!(
+            "content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
+            first.len(),
+            first,
+            second.len(),
+            second
+        );
+        let mut input = stream.as_bytes();
+        assert_eq!(read_message(&mut input).unwrap().unwrap()["method"], "initialize");
+        assert_eq!(read_message(&mut input).unwrap().unwrap()["method"], "exit");
+        assert!(read_message(&mut input).unwrap().is_none());
+
+        let mut headerless = "Content-Type: text/plain\r\n\r\n{}".as_bytes();
+        assert_eq!(read_message(&mut headerless).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn written_messages_read_back() {
+        let message = json!({ "jsonrpc": "2.0", "id": 7, "result": "é" });
+        let mut framed = Vec::new();
+        write_message(&mut framed, &message).unwrap();
+        assert_eq!(read_message(&mut framed.as_slice()).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn uris_are_percent_decoded() {
+        let cases = [
+            ("file:///tmp/a%20b.py", Some("/tmp/a b.py")),
+            ("file:///tmp/caf%C3%A9.rs", Some("/tmp/café.rs")),
+            ("file:///tmp/100%.py", Some("/tmp/100%.py")),
+            ("file:///tmp/%zz.py", Some("/tmp/%zz.py")),
+            ("file:///tmp/%FF.py", None),
+            ("https://example.com/a.py", None),
+        ];
+        for (uri, expected) in cases {
+            assert_eq!(uri_to_path(uri), expected.map(PathBuf::from), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn prepare_picks_the_innermost_function() {
+        let uri = fixture_uri();
+        let at = |line: usize| {
+            request("textDocument/prepareCallHierarchy", json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": 0 } }))
+        };
+        assert_eq!(at(2)[0]["name"], "inner");
+        assert_eq!(at(3)[0]["name"], "outer");
+        assert_eq!(at(7)[0]["data"]["key"], "helper");
+        assert_eq!(at(6), Value::Null);
+
+        let outer = &at(0)[0];
+        assert_eq!(outer["uri"], uri.as_str());
+        assert_eq!(outer["range"], range(0, 0, 6, 0));
+        assert_eq!(outer["selectionRange"], range(0, 4, 0, 9));
+    }
+
+    #[test]
+    fn hierarchy_replies_point_at_the_first_call() {
+        let uri = fixture_uri();
+        let item = |key: &str| json!({ "item": { "uri": uri, "data": { "key": key } } });
+
+        let incoming = request("callHierarchy/incomingCalls", item("helper"));
+        let mut from: Vec<(&str, &Value)> = incoming
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| (call["from"]["name"].as_str().unwrap(), &call["fromRanges"]))
+            .collect();
+        from.sort_by_key(|(name, _)| *name);
+        // `outer` calls `helper` twice; only the first call has a recorded column.
+        assert_eq!(from, [("inner", &json!([range(2, 8, 2, 14)])), ("outer", &json!([range(4, 4, 4, 10)]))]);
+
+        let outgoing = request("callHierarchy/outgoingCalls", item("outer"));
+        let mut to: Vec<(&str, &Value)> = outgoing
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| (call["to"]["name"].as_str().unwrap(), &call["fromRanges"]))
+            .collect();
+        to.sort_by_key(|(name, _)| *name);
+        assert_eq!(to, [("helper", &json!([range(4, 4, 4, 10)])), ("inner", &json!([range(3, 4, 3, 9)]))]);
+
+        assert_eq!(request("callHierarchy/outgoingCalls", item("missing")), json!([]));
+    }
+
+    #[test]
+    fn bad_requests_get_error_codes() {
+        let config = Config::default();
+        assert_eq!(handle("textDocument/hover", &Value::Null, &config).unwrap_err().0, METHOD_NOT_FOUND);
+        let no_position = json!({ "textDocument": { "uri": fixture_uri() } });
+        assert_eq!(handle("textDocument/prepareCallHierarchy", &no_position, &config).unwrap_err().0, INVALID_PARAMS);
+        let not_a_file = json!({ "item": { "uri": "untitled:1", "data": { "key": "f" } } });
+        assert_eq!(handle("callHierarchy/incomingCalls", &not_a_file, &config).unwrap_err().0, INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use schemars::{JsonSchema, schema_for};
+use serde::Serialize;
+use crate::{FnInfo, Location, Visibility, callers, coupling, edges, fan_counts, find_wrappers};
+use crate::graph::CallGraph;
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes one `caller,callee,call_line` row per edge, with 1-based line numbers.
+pub fn write_csv<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>) -> std::io::Result<()> {
+    writeln!(out, "caller,callee,call_line")?;
+    for (caller, callee, line) in edges(hm) {
+        writeln!(out, "{},{},{}", csv_field(caller), csv_field(callee), line + 1)?;
+    }
+    Ok(())
+}
+
+/// One line of [`write_ndjson_edges`].
+#[derive(Serialize)]
+struct JsonEdge<'a> {
+    from: &'a str,
+    to: &'a str,
+    /// 1-based line of the call.
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_file: Option<String>,
+}
+
+/// Writes each edge as its own JSON object on one line, as it is visited, so consumers can
+/// stream the graph (graph databases, `jq -c`) without holding a whole document.
+pub fn write_ndjson_edges<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>) -> std::io::Result<()> {
+    let file = |name: &str| hm.get(name).and_then(|info| info.source_file.as_ref()).map(|path| path.display().to_string());
+    for (caller, callee, line) in edges(hm) {
+        let edge = JsonEdge { from: caller, to: callee, line: line + 1, from_file: file(caller), to_file: file(callee) };
+        serde_json::to_writer(&mut *out, &edge)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes one `caller -> callee` line per distinct edge, sorted, for diffing two runs.
+pub fn write_compact<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>) -> std::io::Result<()> {
+    let lines: BTreeSet<(&str, &str)> = edges(hm).map(|(caller, callee, _)| (caller, callee)).collect();
+    for (caller, callee) in lines {
+        writeln!(out, "{} -> {}", caller, callee)?;
+    }
+    Ok(())
+}
+
+/// Writes the graph as a PlantUML component diagram: one component per function, one arrow per call.
+///
+/// Components get `f<N>` aliases so names with `::`, `<`, spaces and the like need no escaping
+/// beyond swapping `"` for `'` in the label.
+pub fn write_plantuml<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>) -> std::io::Result<()> {
+    let mut names: Vec<&String> = hm.keys().collect();
+    names.sort();
+    let alias: HashMap<&str, usize> = names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+
+    writeln!(out, "@startuml")?;
+    for (i, name) in names.iter().enumerate() {
+        writeln!(out, "component \"{}\" as f{}", name.replace('"', "'"), i)?;
+    }
+    for (caller, callee, _) in edges(hm) {
+        if let (Some(from), Some(to)) = (alias.get(caller), alias.get(callee)) {
+            writeln!(out, "f{} --> f{}", from, to)?;
+        }
+    }
+    writeln!(out, "@enduml")
+}
+
+/// Writes one `name (line N) -> [callee, ...]` line per function, sorted by name, located as `location` says.
+///
+/// Functions nothing calls are tagged `[root]`, or `[orphan]` when they call nothing either;
+/// the sorted `dead` ones (unreachable from every root) are tagged `[orphan]` too, and
+/// functions calling themselves `[recursive]`.
+pub fn write_flat<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, dead: &[String], location: &Location) -> std::io::Result<()> {
+    let callers = callers(hm);
+    let mut names: Vec<&String> = hm.keys().collect();
+    names.sort();
+
+    for name in names {
+        let info = &hm[name];
+        let callees: Vec<&str> = info.callees.iter().map(|(callee, _)| callee.as_str()).collect();
+        let uncalled = callers[name.as_str()].iter().all(|caller| caller == name);
+        let tag = if (uncalled && callees.iter().all(|callee| callee == name)) || dead.binary_search(name).is_ok() {
+            " [orphan]"
+        } else if uncalled {
+            " [root]"
+        } else {
+            ""
+        };
+        let recursive = if info.is_recursive { " [recursive]" } else { "" };
+        writeln!(out, "{} ({}) -> [{}]{}{}", name, location.of(info), callees.join(", "), recursive, tag)?;
+    }
+    Ok(())
+}
+
+/// Writes the functions grouped under the file that defines them, files and functions sorted,
+/// with each function's calls into other files as `-> callee (path)` lines below it.
+///
+/// Paths are shown relative to `root`; functions without a recorded file are listed under `root`.
+pub fn write_by_file<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, root: &Path) -> std::io::Result<()> {
+    let file_of = |name: &str| {
+        hm.get(name).map(|info| match &info.source_file {
+            Some(path) => path.strip_prefix(root).unwrap_or(path).display().to_string(),
+            None => root.display().to_string(),
+        })
+    };
+
+    let mut files: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for name in hm.keys() {
+        files.entry(file_of(name).unwrap_or_default()).or_default().push(name);
+    }
+
+    for (file, mut names) in files {
+        writeln!(out, "{}", file)?;
+        names.sort_by_key(|name| (hm[*name].line_at_call, *name));
+        for name in names {
+            writeln!(out, "  {} (line {})", name, hm[name].line_at_call + 1)?;
+            let mut seen = Vec::new();
+            for (callee, _) in &hm[name].callees {
+                if let Some(target) = file_of(callee)
+                    && target != file
+                    && !seen.contains(&callee)
+                {
+                    writeln!(out, "    -> {} ({})", callee, target)?;
+                    seen.push(callee);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the `n` functions with the highest count, ties broken by name.
+fn write_ranking<W: Write>(
+    out: &mut W,
+    title: &str,
+    unit: &str,
+    mut counts: Vec<(&str, usize)>,
+    hm: &HashMap<String, FnInfo>,
+    n: usize,
+    location: &Location,
+) -> std::io::Result<()> {
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    writeln!(out, "\n{}:", title)?;
+    for (name, count) in counts.into_iter().take(n) {
+        writeln!(out, "  {} ({}): {} {}", name, location.of(&hm[name]), count, unit)?;
+    }
+    Ok(())
+}
+
+/// Ranks functions by fan-in: how many other functions call them, as [`fan_counts`] counts.
+pub fn write_top_callers<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, n: usize, location: &Location) -> std::io::Result<()> {
+    let counts = fan_counts(hm).into_iter().filter(|(name, _)| !hm[*name].is_external).map(|(name, (fan_in, _))| (name, fan_in)).collect();
+    write_ranking(out, &format!("Top {} callers (fan-in)", n), "callers", counts, hm, n, location)
+}
+
+/// Ranks functions by fan-out: how many other functions they call, as [`fan_counts`] counts.
+pub fn write_top_callees<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, n: usize, location: &Location) -> std::io::Result<()> {
+    let counts = fan_counts(hm).into_iter().filter(|(name, _)| !hm[*name].is_external).map(|(name, (_, fan_out))| (name, fan_out)).collect();
+    write_ranking(out, &format!("Top {} callees (fan-out)", n), "callees", counts, hm, n, location)
+}
+
+/// Lists the functions that call anything by the depth of their call tree (`--report depth`),
+/// deepest first.
+pub fn write_depths<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, location: &Location) -> std::io::Result<()> {
+    let counts: Vec<(&str, usize)> = CallGraph::new(hm).subtree_depths().into_iter().filter(|(_, depth)| *depth > 0).collect();
+    let n = counts.len();
+    write_ranking(out, "Call depth (longest chain of calls below each function)", "levels", counts, hm, n, location)
+}
+
+/// Writes the `--report coupling` figures, articulation functions with their locations.
+pub fn write_coupling<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, location: &Location) -> std::io::Result<()> {
+    let report = coupling(hm);
+    writeln!(out, "\nCoupling:")?;
+    writeln!(out, "  functions: {}", report.functions)?;
+    writeln!(out, "  call edges: {}", report.edges)?;
+    writeln!(out, "  density: {:.4}", report.density)?;
+    match report.average_path_length {
+        Some(length) => writeln!(out, "  average path length: {:.2}", length)?,
+        None => writeln!(out, "  average path length: n/a")?,
+    }
+    writeln!(out, "  functions in cycles: {}", report.in_cycles)?;
+    writeln!(out, "  articulation functions: {}", report.articulation_points.len())?;
+    for name in &report.articulation_points {
+        writeln!(out, "    {} ({})", name, location.of(&hm[name]))?;
+    }
+    Ok(())
+}
+
+/// Writes the `--report wrappers` list: each function that only calls external code, with
+/// its location and what it wraps.
+pub fn write_wrappers<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, location: &Location) -> std::io::Result<()> {
+    let wrappers = find_wrappers(hm);
+    writeln!(out, "\nWrappers (functions calling only external code): {}", wrappers.len())?;
+    for name in &wrappers {
+        let info = &hm[name];
+        let mut callees: Vec<&str> = info.callees.iter().map(|(callee, _)| callee.as_str()).collect();
+        callees.sort();
+        writeln!(out, "  {} ({}) -> {}", name, location.of(info), callees.join(", "))?;
+    }
+    Ok(())
+}
+
+/// The `--format json` document.
+#[derive(Serialize, JsonSchema)]
+#[schemars(title = "pars call graph")]
+struct JsonGraph<'a> {
+    /// Every function, sorted by name.
+    functions: Vec<JsonFunction<'a>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonFunction<'a> {
+    name: &'a str,
+    /// File that defines the function, as it was reached from the analyzed path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// 1-based line of the definition, or of the first call for external functions.
+    line: usize,
+    /// 1-based character column of the name on the definition line.
+    def_col: usize,
+    /// The definition's signature on one line, without the body opener.
+    signature: &'a str,
+    /// Reached through an imported module and defined outside the parsed files (`--include-external`).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    external: bool,
+    /// Calls itself directly.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    recursive: bool,
+    /// Kind-changing decorator (`property`, `classmethod`, `staticmethod`, ..).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decorator: Option<&'a str>,
+    /// Declared without a body (a Rust trait method without a default).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    declaration: bool,
+    visibility: Visibility,
+    /// Names before `--normalize-names` rewrote or merged them into `name`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    raw_names: Vec<&'a str>,
+    /// Called functions in call order (sorted under `--deterministic`).
+    callees: Vec<JsonCallee<'a>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonCallee<'a> {
+    name: &'a str,
+    /// 1-based line of the first call.
+    line: usize,
+    /// 1-based character column of the callee's name in that call.
+    call_col: usize,
+    /// The callee is passed by name rather than called (`--include-references`).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reference: bool,
+}
+
+/// Writes the JSON Schema describing [`write_json`]'s output.
+pub fn write_json_schema<W: Write>(out: &mut W) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &schema_for!(JsonGraph))?;
+    writeln!(out)
+}
+
+/// Writes the graph as a JSON object with functions sorted by name and 1-based line numbers, on
+/// one line unless `pretty`.
+pub fn write_json<W: Write>(out: &mut W, hm: &HashMap<String, FnInfo>, pretty: bool) -> std::io::Result<()> {
+    let mut names: Vec<&String> = hm.keys().collect();
+    names.sort();
+
+    let functions = names
+        .into_iter()
+        .map(|name| {
+            let info = &hm[name];
+            JsonFunction {
+                name,
+                file: info.source_file.as_ref().map(|path| path.display().to_string()),
+                line: info.line_at_call + 1,
+                def_col: info.def_col + 1,
+                signature: &info.signature,
+                external: info.is_external,
+                recursive: info.is_recursive,
+                decorator: info.decorator.as_deref(),
+                declaration: info.is_declaration,
+                visibility: info.visibility,
+                raw_names: info.raw_names.iter().map(String::as_str).collect(),
+                callees: info
+                    .callees
+                    .iter()
+                    .map(|(callee, line)| JsonCallee {
+                        name: callee,
+                        line: line + 1,
+                        call_col: info.call_cols.get(callee).map_or(1, |col| col + 1),
+                        reference: info.references.contains(callee),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    if pretty {
+        serde_json::to_writer_pretty(&mut *out, &JsonGraph { functions })?;
+    } else {
+        serde_json::to_writer(&mut *out, &JsonGraph { functions })?;
+    }
+    writeln!(out)
+}
+
+/// The `--format tree-json` document.
+#[derive(Serialize)]
+struct JsonTree<'a> {
+    /// One tree per root, in the order the text tree prints them.
+    roots: Vec<TreeNode<'a>>,
+    /// Functions no root reaches, sorted by name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unreachable: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct TreeNode<'a> {
+    name: &'a str,
+    /// 1-based line of the definition, or of the first call for external functions.
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    external: bool,
+    /// Already shown earlier in the traversal, where its children are listed.
+    #[serde(rename = "ref", skip_serializing_if = "std::ops::Not::not")]
+    repeat: bool,
+    children: Vec<TreeNode<'a>>,
+}
+
+fn tree_node<'a>(name: &'a str, hm: &'a HashMap<String, FnInfo>, visited: &mut HashSet<&'a str>) -> TreeNode<'a> {
+    let info = &hm[name];
+    let repeat = !visited.insert(name);
+    TreeNode {
+        name,
+        line: info.line_at_call + 1,
+        file: info.source_file.as_ref().map(|path| path.display().to_string()),
+        external: info.is_external,
+        repeat,
+        children: if repeat {
+            Vec::new()
+        } else {
+            info.callees.iter().map(|(callee, _)| tree_node(callee, hm, visited)).collect()
+        },
+    }
+}
+
+/// Writes the traversal [`crate::print_tree`] prints from `roots` as nested JSON, each function
+/// expanded once and later occurrences marked as refs, followed by the `unreachable` names.
+pub fn write_tree_json<W: Write>(
+    out: &mut W,
+    hm: &HashMap<String, FnInfo>,
+    roots: &[String],
+    unreachable: &[String],
+    pretty: bool,
+) -> std::io::Result<()> {
+    let mut visited = HashSet::new();
+    let tree = JsonTree {
+        roots: roots.iter().map(|root| tree_node(root, hm, &mut visited)).collect(),
+        unreachable: unreachable.iter().filter(|name| hm.contains_key(*name)).map(String::as_str).collect(),
+    };
+
+    if pretty {
+        serde_json::to_writer_pretty(&mut *out, &tree)?;
+    } else {
+        serde_json::to_writer(&mut *out, &tree)?;
+    }
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::file_info::Language;
+    use crate::parser::parse_source;
+
+    #[test]
+    fn csv_keeps_recursive_calls_as_self_loops() {
+        let source = "def factorial(n):\n    return 1 if n <= 1 else n * factorial(n - 1)\n\ndef main():\n    factorial(5)\n";
+        let hm = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        let mut out = Vec::new();
+        write_csv(&mut out, &hm).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "caller,callee,call_line\nfactorial,factorial,2\nmain,factorial,5\n"
+        );
+    }
+}
@@ -1,139 +1,1899 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use crate::{FnInfo, lang, cache};
-use crate::lang::LangSpec; // Add this import
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::{FnInfo, Visibility, archive, cache};
+use crate::lang::{LangConfig, assigned_name, extract_keyword_name, leading_keyword, starts_with_keyword};
 use crate::file_info::{FileInfo, Language};
+use crate::cli::MergeStrategy;
 use crate::config::Config;
 use crate::error::ParseError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Name of the function standing for a script's entrypoint block (`if __name__ == "__main__":`).
+pub const MAIN_BLOCK: &str = "__main__";
+/// Name of the function standing for a file's top-level code, outside every definition.
+pub const MODULE_BLOCK: &str = "<module>";
 
 pub fn read_file(path: &PathBuf) -> Result<String, ParseError> {
     std::fs::read_to_string(path).map_err(ParseError::from)
 }
 
-fn extract_function_name<L: lang::LangSpec>(def_line: &str) -> Option<String> {
-    let after_def = def_line.trim_start_matches(L::FUNC_DEF).trim();
+/// A file's text, either read into memory or memory-mapped (`--mmap`).
+pub enum SourceText {
+    Read(String),
+    /// A mapping already checked to be valid UTF-8.
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for SourceText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            SourceText::Read(text) => text,
+            // SAFETY: `read_source` only keeps mappings that passed `str::from_utf8`.
+            SourceText::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+/// Reads `path`, memory-mapping it when `mmap` is set so large files aren't copied. A mapped
+/// file that isn't valid UTF-8 is decoded line by line, invalid bytes becoming U+FFFD, instead
+/// of failing like [`read_file`].
+pub fn read_source(path: &PathBuf, mmap: bool) -> Result<SourceText, ParseError> {
+    if !mmap {
+        return read_file(path).map(SourceText::Read);
+    }
+    let file = std::fs::File::open(path)?;
+    // Empty files can't be mapped on every platform, and have nothing to map anyway.
+    if file.metadata()?.len() == 0 {
+        return Ok(SourceText::Read(String::new()));
+    }
+    // SAFETY: the mapping is read-only; a file truncated by another process while mapped is
+    // outside what `pars` defends against, as with any tool reading files being written.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    if std::str::from_utf8(&map).is_ok() {
+        return Ok(SourceText::Mapped(map));
+    }
+    let text = map.split_inclusive(|&byte| byte == b'\n').map(String::from_utf8_lossy).collect();
+    Ok(SourceText::Read(text))
+}
+
+/// Where a parse reads its text: already in memory, or a file read through a buffer on every
+/// pass, so that a large file is never held whole. A leading UTF-8 BOM is dropped either way, as
+/// it would otherwise stick to the first line and hide a definition there.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Text(&'a str),
+    File(&'a Path),
+}
+
+type Lines<'a> = Box<dyn Iterator<Item = Result<Cow<'a, str>, ParseError>> + 'a>;
+
+impl<'a> Source<'a> {
+    /// A fresh walk over the lines.
+    fn lines(self) -> Result<Lines<'a>, ParseError> {
+        match self {
+            Source::Text(text) => {
+                let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+                Ok(Box::new(text.lines().map(|line| Ok(Cow::Borrowed(line)))))
+            }
+            Source::File(path) => {
+                let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+                if reader.fill_buf()?.starts_with("\u{FEFF}".as_bytes()) {
+                    reader.consume("\u{FEFF}".len());
+                }
+                Ok(Box::new(reader.lines().map(|line| Ok(Cow::Owned(line?)))))
+            }
+        }
+    }
+}
+
+/// A line as the passes see it: a whole source line, or the part of one where a definition starts.
+struct Piece<'a> {
+    text: Cow<'a, str>,
+    /// 0-based line of the file it comes from.
+    line: usize,
+    /// Character column it starts at on that line.
+    col: usize,
+}
+
+/// A cut the first pass made: piece number `piece` starts at byte `at` (character `col`) of line `line`.
+struct Split {
+    piece: usize,
+    line: usize,
+    col: usize,
+    at: usize,
+}
+
+/// How [`Pieces`] decides where to cut lines.
+enum Cuts<'s> {
+    /// Look for definitions after the first on each line, refusing lines over the length limit.
+    Find { lang: &'s LangConfig, max_line_length: usize },
+    /// Make the cuts an earlier pass found, the next one first.
+    Replay(&'s [Split]),
+}
+
+/// Splits lines into [`Piece`]s. In brace-scoped languages a line holding several definitions
+/// (generated or macro-expanded code) is also cut before each one after the first, so every
+/// definition starts a piece of its own; elsewhere only the first is parsed, with a warning. The
+/// first pass finds the cuts and later passes replay them, so they number pieces the same way.
+struct Pieces<'a, 's> {
+    lines: Lines<'a>,
+    cuts: Cuts<'s>,
+    /// Cuts found so far, in order.
+    found: Vec<Split>,
+    /// Number of the next file line, and of the next piece.
+    line: usize,
+    piece: usize,
+    /// Pieces of a cut line not handed out yet.
+    pending: VecDeque<Piece<'a>>,
+}
+
+impl<'a, 's> Pieces<'a, 's> {
+    fn find(lang: &'s LangConfig, lines: Lines<'a>, max_line_length: usize) -> Self {
+        Self::new(lines, Cuts::Find { lang, max_line_length })
+    }
+
+    fn replay(lines: Lines<'a>, splits: &'s [Split]) -> Self {
+        Self::new(lines, Cuts::Replay(splits))
+    }
+
+    fn new(lines: Lines<'a>, cuts: Cuts<'s>) -> Self {
+        Self { lines, cuts, found: Vec::new(), line: 0, piece: 0, pending: VecDeque::new() }
+    }
+}
+
+impl<'a> Iterator for Pieces<'a, '_> {
+    type Item = Result<Piece<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(piece) = self.pending.pop_front() {
+            return Some(Ok(piece));
+        }
+        let text = match self.lines.next()? {
+            Ok(text) => text,
+            Err(e) => return Some(Err(e)),
+        };
+        let n = self.line;
+        self.line += 1;
+        let finding = matches!(self.cuts, Cuts::Find { .. });
+        let starts = match &mut self.cuts {
+            Cuts::Find { lang, max_line_length } => {
+                if let Err(e) = check_line_length(n, &text, *max_line_length) {
+                    return Some(Err(e));
+                }
+                let starts = if (lang.extract_name)(lang, text.trim_start()).is_some() { later_definitions(lang, &text) } else { Vec::new() };
+                if !starts.is_empty() && !lang.brace_scopes {
+                    crate::info!("Warning: line {} defines {} functions; only the first is parsed", n + 1, starts.len() + 1);
+                    Vec::new()
+                } else {
+                    starts
+                }
+            }
+            Cuts::Replay(splits) => {
+                let (here, rest) = splits.split_at(splits.iter().take_while(|split| split.line == n).count());
+                *splits = rest;
+                here.iter().map(|split| split.at).collect()
+            }
+        };
+        if starts.is_empty() {
+            self.piece += 1;
+            return Some(Ok(Piece { text, line: n, col: 0 }));
+        }
+
+        if finding {
+            crate::debug!("line {}: split before {} more definitions", n + 1, starts.len());
+        }
+        let mut from = 0;
+        for start in starts.into_iter().chain([text.len()]) {
+            let col = text[..from].chars().count();
+            if finding && from > 0 {
+                self.found.push(Split { piece: self.piece, line: n, col, at: from });
+            }
+            let part = match &text {
+                Cow::Borrowed(line) => {
+                    let line: &'a str = line;
+                    Cow::Borrowed(&line[from..start])
+                }
+                Cow::Owned(line) => Cow::Owned(line[from..start].to_string()),
+            };
+            self.pending.push_back(Piece { text: part, line: n, col });
+            self.piece += 1;
+            from = start;
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// File line and column where piece `i` starts, given the cuts that made the pieces.
+fn origin(splits: &[Split], i: usize) -> (usize, usize) {
+    let before = splits.partition_point(|split| split.piece <= i);
+    let col = splits[..before].last().filter(|split| split.piece == i).map_or(0, |split| split.col);
+    (i - before, col)
+}
+
+fn line_contains_function_call(lang: &LangConfig, line: &str, func_name: &str) -> bool {
+    if lang.message_calls {
+        return line_contains_message_call(lang, line, func_name);
+    }
+    if !line.contains(func_name) {
+        return false;
+    }
+    if lang.bare_calls {
+        return find_token(lang, line, func_name).is_some();
+    }
+    if lang.prefix_calls {
+        return line_contains_prefix_call(lang, line, func_name);
+    }
+    
+    // `name(` only counts as a whole word: `my_name(` is a different function.
+    let pattern = format!("{}{}", func_name, lang.params_open);
+    if line
+        .match_indices(&pattern)
+        .any(|(pos, _)| !line[..pos].ends_with(|c| lang.is_ident_char(c)))
+    {
+        return true;
+    }
+    
+    lang.accessors
+        .iter()
+        .any(|accessor| line.contains(&format!("{}{}", accessor, pattern)))
+}
+
+/// Byte position of a bare mention of `func_name` that isn't a call: a whole word, not reached
+/// through an accessor and not followed by a parameter list (`register(callback)`). Names being
+/// assigned to or labelled (`name = ..`, `name: ..`) are bindings, not mentions.
+fn function_reference(lang: &LangConfig, line: &str, func_name: &str) -> Option<usize> {
+    line.match_indices(func_name).map(|(pos, _)| pos).find(|&pos| {
+        let before = &line[..pos];
+        let after = &line[pos + func_name.len()..];
+        let next = after.trim_start();
+        let binding = next.strip_prefix('=').is_some_and(|rest| !rest.starts_with('='))
+            || next.strip_prefix(':').is_some_and(|rest| !rest.starts_with(':'));
+        !before.ends_with(|c| lang.is_ident_char(c))
+            && !after.starts_with(|c| lang.is_ident_char(c))
+            && !next.starts_with(lang.params_open.as_str())
+            && !binding
+            && !lang.accessors.iter().any(|accessor| before.ends_with(accessor.as_str()))
+    })
+}
+
+/// Like [`line_contains_function_call`], but the name must also appear unqualified or on
+/// `self`/`Self`/`this`: a function calling itself doesn't go through another module's path
+/// (`net::connect(` inside `connect`, `MyApp.Accounts.create(` inside `Other.create`).
+fn line_contains_own_call(lang: &LangConfig, line: &str, func_name: &str) -> bool {
+    // Any receiver may be `self` under another name; selectors carry no path to compare.
+    if lang.message_calls {
+        return line_contains_function_call(lang, line, func_name);
+    }
+    let unqualified = line.match_indices(func_name).any(|(pos, _)| {
+        let before = &line[..pos];
+        let whole_word = !before.ends_with(|c| lang.is_ident_char(c))
+            && !line[pos + func_name.len()..].starts_with(|c| lang.is_ident_char(c));
+        let qualified = lang.accessors.iter().any(|accessor| {
+            before.strip_suffix(accessor.as_str()).is_some_and(|receiver| {
+                let receiver = receiver.rsplit(|c| !lang.is_ident_char(c)).next().unwrap_or_default();
+                !["self", "Self", "this"].contains(&receiver)
+            })
+        });
+        whole_word && !qualified
+    });
+    unqualified && line_contains_function_call(lang, line, func_name)
+}
+
+/// Matches a Lisp-style call, `(name ..)` or `(ns/name ..)`.
+fn line_contains_prefix_call(lang: &LangConfig, line: &str, func_name: &str) -> bool {
+    line.match_indices(func_name).any(|(pos, _)| {
+        let before = &line[..pos];
+        let whole_word = !line[pos + func_name.len()..].starts_with(|c| lang.is_ident_char(c));
+        let in_call_position = before.ends_with('(')
+            || lang.accessors.iter().any(|accessor| {
+                before
+                    .strip_suffix(accessor.as_str())
+                    .is_some_and(|ns| ns.trim_end_matches(|c| lang.is_ident_char(c) || c == '.').ends_with('('))
+            });
+        whole_word && in_call_position
+    })
+}
+
+/// Matches a message send to `selector`: `[receiver name]` for `name`, `[receiver name:a other:b]`
+/// for `name:other:`. The receiver is a name (`self`, `obj.items`), a call or a nested send
+/// (`[[Foo alloc] init]`); a send continuing on the next line matches as far as it goes.
+fn line_contains_message_call(lang: &LangConfig, line: &str, selector: &str) -> bool {
+    let mut parts = selector.split_terminator(':');
+    let Some(first) = parts.next() else {
+        return false;
+    };
+    if !line.contains(first) {
+        return false;
+    }
+    let labels: Vec<&str> = parts.collect();
+    let ident_end = |text: &str| text.find(|c: char| !lang.is_ident_char(c)).unwrap_or(text.len());
+
+    line.match_indices('[').any(|(open, _)| {
+        let mut rest = &line[open + 1..];
+        rest = match rest.trim_start().strip_prefix('[') {
+            Some(nested) => match bracket_close(nested, '[', ']') {
+                Some(close) => &nested[close + 1..],
+                None => return false,
+            },
+            None => {
+                let receiver = rest.trim_start();
+                let end = receiver.find(|c: char| !(lang.is_ident_char(c) || c == '.')).unwrap_or(receiver.len());
+                if end == 0 {
+                    return false;
+                }
+                let after = &receiver[end..];
+                match after.strip_prefix('(') {
+                    Some(args) => match bracket_close(args, '(', ')') {
+                        Some(close) => &args[close + 1..],
+                        None => return false,
+                    },
+                    None => after,
+                }
+            }
+        };
+        if !rest.starts_with(char::is_whitespace) {
+            return false;
+        }
+        rest = rest.trim_start();
+        let end = ident_end(rest);
+        if &rest[..end] != first {
+            return false;
+        }
+        let after = &rest[end..];
+        if !selector.ends_with(':') {
+            return after.trim_start().starts_with(']');
+        }
+        match after.strip_prefix(':') {
+            Some(args) if !args.starts_with(':') => {
+                let (sent, closed) = message_labels(lang, args);
+                if closed { sent == labels } else { labels.starts_with(&sent) }
+            }
+            _ => false,
+        }
+    })
+}
+
+/// The labels (`other` in `other:b`) of a message's arguments after its first one, and whether
+/// the message's closing `]` is on the line. Labels inside nested brackets and strings don't count.
+fn message_labels<'a>(lang: &LangConfig, args: &'a str) -> (Vec<&'a str>, bool) {
+    let mut labels = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut chars = args.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '(' | '[' | '{' => depth += 1,
+            ']' if depth == 0 => return (labels, true),
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && lang.is_ident_char(c) && !args[..pos].ends_with(|c| lang.is_ident_char(c)) => {
+                let end = args[pos..].find(|c: char| !lang.is_ident_char(c)).map_or(args.len(), |end| pos + end);
+                let after = &args[end..];
+                if after.starts_with(':') && !after.starts_with("::") {
+                    labels.push(&args[pos..end]);
+                }
+            }
+            _ => {}
+        }
+    }
+    (labels, false)
+}
+
+/// Position of the bracket closing one whose opening `open` was just stripped from `text`.
+fn bracket_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 1;
+    text.char_indices().find_map(|(pos, c)| {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+        }
+        (depth == 0).then_some(pos)
+    })
+}
+
+/// A function seen while scanning, along with the `impl` block it was defined in (if any).
+struct Definition {
+    key: String,
+    name: String,
+    owner: Option<ImplBlock>,
+    line: usize,
+    /// Character column of the name on that line (0 under `--edges-only`, which drops positions).
+    col: usize,
+    signature: String,
+    /// Test code: marked (`#[test]`), named (`test_*`) or nested in a test module/class.
+    is_test: bool,
+    /// Defined with `macro_rules!`; keyed as `name!` and only matched by `name!(..)`-style invocations.
+    is_macro: bool,
+    /// An assigned lambda (`name = lambda x: ..`), whose whole line is its body.
+    is_assigned: bool,
+    /// Kind-changing decorator on the definition (`property`, `staticmethod`, ..).
+    decorator: Option<String>,
+    /// A signature ending in `;` with no body (`fn area(&self) -> f64;` in a trait).
+    is_declaration: bool,
+    visibility: Visibility,
+}
+
+/// A definition whose body the second pass is inside.
+struct Scope {
+    /// Index in the definitions list.
+    idx: usize,
+    /// Indentation of the header line.
+    indent: usize,
+    /// Brace depth before the header line (brace-scoped languages).
+    depth: usize,
+    /// The body's `{` has been seen.
+    opened: bool,
+    /// The body's braces balanced; the scope ends after the current line.
+    closed: bool,
+}
+
+/// Tracks `{`/`}` nesting line by line, skipping braces inside comments, strings and char literals.
+#[derive(Default)]
+struct BraceCounter {
+    depth: usize,
+    in_block_comment: bool,
+    /// Inside a string literal, with its closing quote and the number of `#`s after it closing
+    /// a raw string (`r#"..."#`).
+    in_string: Option<(char, usize)>,
+}
+
+impl BraceCounter {
+    /// Advances past `line` and returns the deepest nesting reached on it.
+    fn scan(&mut self, lang: &LangConfig, line: &str) -> usize {
+        let mut peak = self.depth;
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if self.in_block_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        self.in_block_comment = false;
+                        rest = &rest[end + 2..];
+                    }
+                    None => break,
+                }
+                continue;
+            }
+            if let Some((quote, hashes)) = self.in_string {
+                if rest.starts_with('\\') && hashes == 0 {
+                    rest = rest.get(2..).unwrap_or_default();
+                    continue;
+                }
+                if let Some(after) = rest.strip_prefix(quote)
+                    && after.as_bytes().get(..hashes).is_some_and(|closing| closing.iter().all(|&b| b == b'#'))
+                {
+                    self.in_string = None;
+                    rest = &after[hashes..];
+                    continue;
+                }
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            if lang.line_comments.iter().any(|comment| rest.starts_with(comment.as_str())) {
+                break;
+            }
+            if rest.starts_with("/*") {
+                self.in_block_comment = true;
+                rest = &rest[2..];
+                continue;
+            }
+            if lang.lifetimes
+                && let Some(raw) = rest.strip_prefix('r')
+            {
+                let hashes = raw.len() - raw.trim_start_matches('#').len();
+                if raw[hashes..].starts_with('"') {
+                    self.in_string = Some(('"', hashes));
+                    rest = &raw[hashes + 1..];
+                    continue;
+                }
+            }
+            match c {
+                '"' => self.in_string = Some(('"', 0)),
+                // A char literal ('{', '\'', '\u{7b}'), not a lifetime ('a).
+                '\'' if lang.lifetimes => {
+                    let literal = rest[1..].strip_prefix('\\').map_or_else(
+                        || rest[1..].chars().next().map(|c| 1 + c.len_utf8()).filter(|&end| rest[end..].starts_with('\'')),
+                        |escaped| escaped.char_indices().skip(1).find(|&(_, c)| c == '\'').map(|(end, _)| end + 2),
+                    );
+                    if let Some(end) = literal {
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+                // Elsewhere it quotes a string (Dart) or char literal (`'{'` in Objective-C).
+                '\'' => self.in_string = Some(('\'', 0)),
+                '{' => {
+                    self.depth += 1;
+                    peak = peak.max(self.depth);
+                }
+                '}' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        peak
+    }
+}
+
+/// What the first pass learned about each line, replayed by the second pass.
+#[derive(Clone, Copy)]
+enum LineKind {
+    /// First line of the definition at this index in the definitions list.
+    Definition(usize),
+    /// A comment.
+    Skip,
+    /// Continuation of a multi-line signature.
+    Signature,
+    /// An `impl` header or closing brace; no function is in scope afterwards.
+    ScopeBreak,
+    Body,
+}
+
+/// The type (and trait, for `impl Trait for Type`) of an enclosing Rust `impl` block.
+#[derive(Clone)]
+struct ImplBlock {
+    self_ty: String,
+    trait_name: Option<String>,
+    /// Indentation of the header, or its brace depth in brace-scoped languages.
+    level: usize,
+}
+
+impl ImplBlock {
+    fn qualify(&self, name: &str) -> String {
+        match &self.trait_name {
+            Some(trait_name) => format!("<{} as {}>::{}", self.self_ty, trait_name, name),
+            None => format!("{}::{}", self.self_ty, name),
+        }
+    }
+}
+
+/// Strips a leading generic parameter list (`<T: Clone, U>`) from `s`.
+fn strip_leading_generics(s: &str) -> &str {
+    if !s.starts_with('<') {
+        return s;
+    }
+    let mut depth = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return s[idx + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Reduces a type path such as `&'a mut std::fmt::Formatter<'b>` to its bare name (`Formatter`).
+fn bare_type_name(ty: &str) -> String {
+    let ty = ty.trim_start_matches('&').trim_start();
+    let ty = if ty.starts_with('\'') {
+        ty.split_once(' ').map_or("", |(_, rest)| rest)
+    } else {
+        ty
+    };
+    let ty = ty.trim_start_matches("mut ").trim_start_matches("dyn ").trim();
+    let ty = ty.split('<').next().unwrap_or(ty);
+    ty.rsplit("::").next().unwrap_or(ty).trim().to_string()
+}
+
+fn parse_impl_header(trimmed: &str, level: usize) -> Option<ImplBlock> {
+    let rest = trimmed.strip_prefix("unsafe ").unwrap_or(trimmed);
+    let rest = rest.strip_prefix("impl")?;
+    if !rest.starts_with(|c: char| c.is_whitespace() || c == '<') {
+        return None;
+    }
+    let rest = strip_leading_generics(rest.trim_start());
+    let header = rest.split('{').next().unwrap_or(rest);
+    let header = header.split(" where").next().unwrap_or(header).trim();
+
+    let (trait_name, self_ty) = match header.split_once(" for ") {
+        Some((trait_part, ty)) => (Some(bare_type_name(trait_part.trim_start_matches('!'))), ty),
+        None => (None, header),
+    };
+    let self_ty = bare_type_name(self_ty);
+    if self_ty.is_empty() {
+        return None;
+    }
+
+    Some(ImplBlock { self_ty, trait_name, level })
+}
+
+fn line_contains_macro_call(line: &str, name: &str) -> bool {
+    ["!(", "![", "!{"]
+        .iter()
+        .any(|bang| line.contains(&format!("{}{}", name, bang)))
+}
+
+/// Extracts `name` from a `macro_rules! name {` line.
+fn extract_macro_name(lang: &LangConfig, trimmed: &str) -> Option<String> {
+    let rest = trimmed.strip_prefix(lang.macro_def.as_deref()?)?.trim_start();
+    let name: String = rest.chars().take_while(|&c| lang.is_ident_char(c)).collect();
+    (lang.is_valid_identifier)(&name).then_some(name)
+}
+
+/// Checks whether `line`, inside a function belonging to `caller_owner`, calls `def`.
+fn line_calls_definition(lang: &LangConfig, line: &str, def: &Definition, caller_owner: Option<&ImplBlock>, ambiguous: bool) -> bool {
+    if def.is_macro {
+        return line_contains_macro_call(line, &def.name);
+    }
+    let Some(owner) = &def.owner else {
+        return line_contains_function_call(lang, line, &def.name);
+    };
+
+    if line.contains(&format!("{}::{}(", owner.self_ty, def.name)) {
+        return true;
+    }
+    if let Some(trait_name) = &owner.trait_name
+        && line.contains(&format!("{}::{}(", trait_name, def.name))
+    {
+        return true;
+    }
+    if caller_owner.is_some_and(|caller| caller.self_ty == owner.self_ty)
+        && (line.contains(&format!("Self::{}(", def.name)) || line.contains(&format!("self.{}(", def.name)))
+    {
+        return true;
+    }
+
+    // A bare `.name(` on some other receiver only counts when no other type defines `name`.
+    !ambiguous && line.contains(&format!(".{}(", def.name))
+}
+
+/// Byte offset of the `params_open` that starts the parameter list, skipping any inside a generic
+/// parameter list such as `<F: Fn(u8) -> T>`.
+fn params_start(def: &str, params_open: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in def.char_indices() {
+        let before = &def[..idx];
+        match c {
+            // Only `name<` opens generics; `x < y` and R's `<-` don't.
+            '<' if before.ends_with(|p: char| p.is_alphanumeric() || p == '_') => depth += 1,
+            '>' if depth > 0 && !before.ends_with(['-', '=']) => depth -= 1,
+            _ if depth == 0 && def[idx..].starts_with(params_open) => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether the first parameter is a `self` receiver (`self`, `&self`, `&'a mut self`, Python's `self`).
+fn takes_self(lang: &LangConfig, signature: &str) -> bool {
+    let Some(open) = params_start(signature, &lang.params_open) else {
+        return false;
+    };
+    let first = signature[open + lang.params_open.len()..].trim_start();
+    let mut receiver = first.trim_start_matches('&').trim_start();
+    if receiver.starts_with('\'') {
+        receiver = receiver.split_once(char::is_whitespace).map_or("", |(_, rest)| rest.trim_start());
+    }
+    if let Some(rest) = receiver.strip_prefix("mut ") {
+        receiver = rest.trim_start();
+    }
+    receiver.strip_prefix("self").is_some_and(|rest| !rest.starts_with(|c| lang.is_ident_char(c)))
+}
+
+/// Byte offset just past the `params_close` that balances the first `params_open` in `def`.
+fn params_end(def: &str, params_open: &str, params_close: &str) -> Option<usize> {
+    let open_pos = params_start(def, params_open)?;
+    let mut depth = 0i32;
+    let mut pos = open_pos;
+    while pos < def.len() {
+        let rest = &def[pos..];
+        if rest.starts_with(params_open) {
+            depth += 1;
+            pos += params_open.len();
+        } else if rest.starts_with(params_close) {
+            depth -= 1;
+            pos += params_close.len();
+            if depth == 0 {
+                return Some(pos);
+            }
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Finds `token` in `haystack`; word tokens (`do`) must stand alone rather than start `do_thing`.
+fn find_token(lang: &LangConfig, haystack: &str, token: &str) -> Option<usize> {
+    // An empty token (Lisp forms have no body opener) is found straight away.
+    if token.is_empty() {
+        return Some(0);
+    }
+    let is_word = |c: char| lang.is_ident_char(c);
+    if !token.chars().all(is_word) {
+        return haystack.find(token);
+    }
+    haystack.match_indices(token).map(|(pos, _)| pos).find(|&pos| {
+        !haystack[..pos].ends_with(is_word) && !haystack[pos + token.len()..].starts_with(is_word)
+    })
+}
+
+/// Position and length of the first body opener after a signature: [`LangConfig::end_def`], or
+/// the expression-body arrow (`=> expr;`) where the language has one.
+fn body_opener(lang: &LangConfig, tail: &str) -> Option<(usize, usize)> {
+    let block = find_token(lang, tail, &lang.end_def).map(|pos| (pos, lang.end_def.len()));
+    let expression = lang.expression_body.as_deref().and_then(|arrow| find_token(lang, tail, arrow).map(|pos| (pos, arrow.len())));
+    block.into_iter().chain(expression).min()
+}
+
+/// End of the parameter list, unless the language allows omitting it and the body opener comes first
+/// (`def name do`, `f x = g (h x)`), in which case any parentheses belong to the body.
+fn params_span(lang: &LangConfig, def: &str) -> Option<usize> {
+    if lang.optional_params
+        && let Some(opener) = find_token(lang, def, &lang.end_def)
+        && def.find(lang.params_open.as_str()).is_none_or(|open| opener < open)
+    {
+        return None;
+    }
+    params_end(def, &lang.params_open, &lang.params_close)
+}
+
+/// Returns true once `def` holds the whole signature: balanced parameters followed by the body opener.
+fn signature_complete(lang: &LangConfig, def: &str) -> bool {
+    match params_span(lang, def) {
+        Some(pos) => body_opener(lang, &def[pos..]).is_some() || def[pos..].trim_end().ends_with(';'),
+        None => lang.optional_params && find_token(lang, def, &lang.end_def).is_some(),
+    }
+}
+
+/// Cuts a (possibly multi-line) definition down to its signature on one clean line:
+/// `fn add(\n    a: i32,\n) -> i32 {` becomes `fn add(a: i32) -> i32`.
+fn normalize_signature(lang: &LangConfig, def: &str) -> String {
+    let head = match params_span(lang, def) {
+        Some(pos) => {
+            let tail = &def[pos..];
+            let cut = [body_opener(lang, tail).map(|(pos, _)| pos), tail.find(';')]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap_or(tail.len());
+            &def[..pos + cut]
+        }
+        None => &def[..find_token(lang, def, &lang.end_def).unwrap_or(def.len())],
+    };
+
+    head.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(",)", ")")
+        .trim_end_matches(',')
+        .to_string()
+}
+
+/// Net change in `do`/`end` block depth across `line`, for languages with keyword-delimited bodies.
+fn block_delta(lang: &LangConfig, line: &str) -> i32 {
+    let Some(block_end) = &lang.block_end else {
+        return 0;
+    };
+    let code = lang
+        .line_comments
+        .iter()
+        .filter_map(|comment| line.find(comment.as_str()))
+        .min()
+        .map_or(line, |pos| &line[..pos]);
+
+    let code: Cow<str> = if lang.brackets_hide_block_words {
+        let mut depth = 0usize;
+        code.chars()
+            .map(|c| match c {
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    ' '
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    ' '
+                }
+                _ if depth > 0 => ' ',
+                _ => c,
+            })
+            .collect::<String>()
+            .into()
+    } else {
+        code.into()
+    };
+
+    // `:` stays part of the word so keyword-list `do:` and atoms like `:end` don't count.
+    code.split(|c: char| !(lang.is_ident_char(c) || c == ':'))
+        .map(|word| {
+            if lang.block_openers.iter().any(|opener| opener == word) {
+                1
+            } else if word == block_end {
+                -1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+/// 0-based character column of `name` as a whole word in `code`, the text of `line` from byte
+/// `offset` on (possibly masked); the start of `code` if the name isn't found there.
+fn name_column(lang: &LangConfig, line: &str, offset: usize, code: &str, name: &str) -> usize {
+    // A selector (`name:other:`) is spread over the line; its first part marks where it starts.
+    let name = if lang.message_calls { name.split(':').next().unwrap_or(name) } else { name };
+    let pos = find_token(lang, code, name).map_or(offset, |pos| offset + pos);
+    line[..pos].chars().count()
+}
+
+/// Adds the names bound to modules by an import statement on `line`: `import os.path` binds
+/// `os`, `import numpy as np` binds `np`.
+fn note_imports(lang: &LangConfig, line: &str, modules: &mut Vec<String>) {
+    let trimmed = line.trim_start();
+    let Some(keyword) = lang.imports.iter().find(|keyword| starts_with_keyword(trimmed, keyword)) else {
+        return;
+    };
+    let code = lang.line_comments.iter().filter_map(|comment| trimmed.find(comment.as_str())).min().map_or(trimmed, |pos| &trimmed[..pos]);
+    for item in code[keyword.len()..].split(',') {
+        let name = match item.split_once(" as ") {
+            Some((_, alias)) => alias.trim(),
+            None => item.trim().split('.').next().unwrap_or_default(),
+        };
+        if (lang.is_valid_identifier)(name) && !modules.iter().any(|module| module == name) {
+            modules.push(name.to_string());
+        }
+    }
+}
+
+/// Blanks out calls qualified by an imported module (`utils.helper(`, `os.path.join(`) so they
+/// can't match a local function of the same name, and returns those callees with their byte offsets.
+/// The masked text keeps `code`'s byte positions.
+fn mask_module_calls<'a>(lang: &LangConfig, code: &'a str, modules: &[String]) -> (Cow<'a, str>, Vec<(String, usize)>) {
+    let mut calls = Vec::new();
+    for module in modules {
+        for (pos, _) in code.match_indices(module.as_str()) {
+            if code[..pos].ends_with(|c: char| lang.is_ident_char(c) || c == '.') {
+                continue;
+            }
+            let rest = &code[pos + module.len()..];
+            if !rest.starts_with('.') {
+                continue;
+            }
+            let len = module.len() + rest.find(|c: char| !(lang.is_ident_char(c) || c == '.')).unwrap_or(rest.len());
+            let chain = &code[pos..pos + len];
+            if !chain.ends_with('.') && code[pos + len..].starts_with(lang.params_open.as_str()) {
+                calls.push((chain.to_string(), pos));
+            }
+        }
+    }
+    if calls.is_empty() {
+        return (Cow::Borrowed(code), calls);
+    }
+
+    calls.sort_by_key(|(_, pos)| *pos);
+    let mut masked = code.to_string();
+    for (chain, pos) in &calls {
+        masked.replace_range(*pos..pos + chain.len(), &" ".repeat(chain.len()));
+    }
+    (Cow::Owned(masked), calls)
+}
+
+/// The code following the body opener on a single-line definition, if there is any.
+fn inline_body<'a>(lang: &LangConfig, line: &'a str) -> Option<&'a str> {
+    let params = match params_span(lang, line) {
+        Some(pos) => pos,
+        None if lang.optional_params => 0,
+        None => return None,
+    };
+    let (opener, len) = body_opener(lang, &line[params..])?;
+    let body = line[params + opener + len..].trim();
+    (!body.is_empty()).then_some(body)
+}
+
+/// The decorator's name if it is one of the language's kind decorators: `@property`,
+/// `@abc.abstractmethod` (matched by its last segment) or `@staticmethod()`.
+fn decorator_kind(lang: &LangConfig, trimmed: &str) -> Option<String> {
+    let annotation = lang.annotation_prefixes.iter().find_map(|prefix| trimmed.strip_prefix(prefix.as_str()))?;
+    let path = annotation.split(|c: char| c == '(' || c.is_whitespace()).next()?;
+    let name = path.rsplit('.').next()?;
+    lang.kind_decorators.iter().any(|kind| kind == name).then(|| name.to_string())
+}
+
+fn kind_rank(lang: &LangConfig, kind: &str) -> usize {
+    lang.kind_decorators.iter().position(|known| known == kind).unwrap_or(usize::MAX)
+}
+
+/// Whether a complete signature declares a function without defining it: `fn area(&self) -> f64;`.
+fn is_declaration(lang: &LangConfig, def: &str) -> bool {
+    params_span(lang, def).is_some_and(|pos| body_opener(lang, &def[pos..]).is_none() && def.trim_end().ends_with(';'))
+}
+
+/// The part of a definition line that can call other functions: its inline body, plus the
+/// parameter list where defaults are evaluated calls (`def f(x=helper()):`).
+fn definition_code<'a>(lang: &LangConfig, line: &'a str) -> Option<&'a str> {
+    if lang.default_args
+        && let Some(open) = params_start(line, &lang.params_open)
+    {
+        return Some(&line[open..]);
+    }
+    inline_body(lang, line)
+}
+
+/// The variable bound by a local assignment such as `let mut name = ..` or `name = ..`.
+fn local_binding<'a>(lang: &LangConfig, trimmed: &'a str) -> Option<&'a str> {
+    lang.local_bindings.iter().find_map(|prefix| {
+        let rest = trimmed.strip_prefix(prefix.as_str())?;
+        let end = rest.find(|c: char| !lang.is_ident_char(c)).unwrap_or(rest.len());
+        let (name, after) = rest.split_at(end);
+        let after = after.trim_start();
+        // A keyword prefix (`let`) is enough; a bare name needs `=` (or a `: type` annotation) after it.
+        let assigns = !prefix.is_empty() || (after.starts_with('=') && !after.starts_with("==")) || after.starts_with(": ");
+        ((lang.is_valid_identifier)(name) && assigns).then_some(name)
+    })
+}
+
+/// Extracts `Name` from a module header and whether it opens a block.
+///
+/// Blocks are `defmodule Name do`, `mod name {` or `class Name:`. Where the language allows it, a
+/// statement (`package Name;`) names the namespace for the rest of the file; otherwise bodiless
+/// declarations (`mod name;`) are not namespaces here.
+fn module_header(lang: &LangConfig, trimmed: &str) -> Option<(String, bool)> {
+    let keyword = lang.module_defs.iter().find(|keyword| starts_with_keyword(trimmed, keyword))?;
+    let block = find_token(lang, trimmed, &lang.end_def).is_some();
+    let statement = lang.module_statements && trimmed.trim_end().ends_with(';');
+    if !block && !statement {
+        return None;
+    }
+    let name: String = trimmed[keyword.len()..]
+        .trim_start()
+        .chars()
+        .take_while(|&c| lang.is_ident_char(c) || c == '.' || c == ':')
+        .collect();
+    let name = name.trim_end_matches(':');
+    (!name.is_empty()).then(|| (name.to_string(), block))
+}
+
+/// Width of the line's indentation, a tab advancing to the next multiple of 8 columns as Python
+/// counts it, so tab- and space-indented code nests the same way.
+fn leading_indent(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .fold(0, |width, c| if c == '\t' { width / 8 * 8 + 8 } else { width + 1 })
+}
+
+/// Picks the language description for `file_info`, applying any keyword overrides from `config`.
+pub fn lang_config(file_info: &FileInfo, config: &Config) -> Result<LangConfig, ParseError> {
+    let extension = file_info.file_path.extension().and_then(|ext| ext.to_str());
+    let custom = config
+        .custom_langs
+        .iter()
+        .find(|lang| extension.is_some_and(|ext| lang.extensions.iter().any(|e| e == ext)));
+
+    let mut lang = match (custom, &file_info.file_type, &config.def_keyword) {
+        (Some(lang), _, _) => lang.clone(),
+        (None, Language::Unknown, Some(keyword)) => LangConfig::generic(keyword),
+        (None, language, _) => LangConfig::try_from(language)?,
+    };
+
+    if let Some(keyword) = &config.def_keyword {
+        lang.func_defs = vec![keyword.clone()];
+        lang.extract_name = extract_keyword_name;
+    }
+    if let Some(body_open) = &config.body_open {
+        lang.end_def = body_open.clone();
+    }
+    if let Some(builtins) = &config.builtins {
+        lang.builtins = builtins.clone();
+    }
+
+    Ok(lang)
+}
+
+/// The first pass's [`LineKind`] of each piece, kept only where the text alone doesn't tell: plain
+/// body lines and comments are left out, so this grows with the definitions rather than the file.
+#[derive(Default)]
+struct LineKinds(HashMap<usize, LineKind>);
+
+impl LineKinds {
+    fn mark(&mut self, i: usize, kind: LineKind) {
+        if !matches!(kind, LineKind::Body | LineKind::Skip) {
+            self.0.insert(i, kind);
+        }
+    }
+
+    /// Kind of piece `i`, whose text is `line`.
+    fn of(&self, lang: &LangConfig, i: usize, line: &str) -> LineKind {
+        match self.0.get(&i) {
+            Some(kind) => *kind,
+            None if lang.is_comment(line.trim_start()) => LineKind::Skip,
+            None => LineKind::Body,
+        }
+    }
+}
+
+/// First pass: find every definition so calls to functions defined further down still resolve.
+fn collect_definitions<'a>(
+    lang: &LangConfig,
+    config: &Config,
+    mut pieces: impl Iterator<Item = Result<Piece<'a>, ParseError>>,
+) -> Result<(Vec<Definition>, LineKinds), ParseError> {
+    let mut definitions = Vec::new();
+    let mut kinds = LineKinds::default();
+    let column = |line: &str, name: &str| if config.edges_only { 0 } else { name_column(lang, line, 0, line, name) };
+    let mut current_impl: Option<ImplBlock> = None;
+    // A test marker waiting for the definition (or `mod`/`class` block) it applies to.
+    let mut pending_test = false;
+    // A kind decorator (`@property`) waiting for the definition it applies to.
+    let mut pending_decorator: Option<String> = None;
+    // Level of the enclosing test module/class header, if any.
+    let mut test_block: Option<usize> = None;
+    // Enclosing modules (name, header level), outermost first.
+    let mut modules: Vec<(String, usize)> = Vec::new();
+    // Namespace set by the last statement-form header (`package Name;`).
+    let mut file_module: Option<String> = None;
+    // Open `do`/`end` depth of the current keyword-delimited body.
+    let mut block_depth: Option<i32> = None;
+    // That body has ended, so the next plain line is outside any function.
+    let mut scope_closed = false;
+    // Enclosing trait definition (header level, visibility), whose methods share its visibility.
+    let mut trait_block: Option<(usize, Visibility)> = None;
+    // Brace nesting, which places blocks in brace-scoped languages whatever their indentation.
+    let mut braces = BraceCounter::default();
+    let mut i = 0;
+
+    while let Some(piece) = pieces.next() {
+        let piece = piece?;
+        let line: &str = &piece.text;
+        let trimmed = line.trim_start();
+        i += 1;
+        // How deep the line sits: its indentation, or in brace-scoped languages the shallowest brace
+        // depth on it, so a block's closing `}` is back at its header's level even in column 0.
+        let level = if lang.brace_scopes {
+            let before = braces.depth;
+            braces.scan(lang, line);
+            before.min(braces.depth)
+        } else {
+            leading_indent(line)
+        };
+
+        if lang.is_comment(trimmed) {
+            kinds.mark(i - 1, LineKind::Skip);
+            continue;
+        }
+
+        if let Some(block_indent) = test_block
+            && !trimmed.is_empty()
+            && level <= block_indent
+        {
+            test_block = None;
+        }
+
+        let mut closed_module = false;
+        while let Some((_, module_indent)) = modules.last()
+            && !trimmed.is_empty()
+            && level <= *module_indent
+        {
+            modules.pop();
+            closed_module = true;
+        }
+        if closed_module && (lang.block_end.as_deref() == Some(trimmed.trim_end()) || trimmed.starts_with('}')) {
+            scope_closed = false;
+            kinds.mark(i - 1, LineKind::ScopeBreak);
+            continue;
+        }
+
+        if let Some((name, block)) = module_header(lang, trimmed) {
+            if !block {
+                file_module = Some(name);
+                kinds.mark(i - 1, LineKind::ScopeBreak);
+                continue;
+            }
+            let test_header = lang.test_block_prefixes.iter().any(|prefix| trimmed.starts_with(prefix.as_str()));
+            if test_block.is_none() && (pending_test || test_header) {
+                test_block = Some(level);
+            }
+            pending_test = false;
+            modules.push((name, level));
+            scope_closed = false;
+            kinds.mark(i - 1, LineKind::ScopeBreak);
+            continue;
+        }
+
+        if lang.test_markers.iter().any(|marker| trimmed.starts_with(marker.as_str())) {
+            pending_test = true;
+            kinds.mark(i - 1, LineKind::Body);
+            continue;
+        }
+
+        if let Some(block) = &current_impl
+            && trimmed.starts_with('}')
+            && level <= block.level
+        {
+            current_impl = None;
+            kinds.mark(i - 1, LineKind::ScopeBreak);
+            continue;
+        }
+
+        if let Some((trait_indent, _)) = trait_block
+            && trimmed.starts_with('}')
+            && level <= trait_indent
+        {
+            trait_block = None;
+        }
+        if lang.qualify_impls
+            && let Some(visibility) = trait_header(lang, trimmed)
+        {
+            trait_block = Some((level, visibility));
+        }
+
+        if lang.qualify_impls
+            && let Some(block) = parse_impl_header(trimmed, level)
+        {
+            current_impl = Some(block);
+            kinds.mark(i - 1, LineKind::ScopeBreak);
+            continue;
+        }
+
+        if config.include_macros
+            && let Some(name) = extract_macro_name(lang, trimmed)
+        {
+            kinds.mark(i - 1, LineKind::Definition(definitions.len()));
+            definitions.push(Definition {
+                visibility: (lang.visibility)(trimmed, &name),
+                key: format!("{}!", name),
+                col: column(line, &name),
+                name,
+                owner: None,
+                line: i - 1,
+                signature: trimmed.trim_end().trim_end_matches('{').trim_end().to_string(),
+                is_test: false,
+                is_macro: true,
+                is_assigned: false,
+                decorator: None,
+                is_declaration: false,
+            });
+            continue;
+        }
+
+        if leading_indent(line) == 0 && is_main_guard(lang, trimmed) {
+            kinds.mark(i - 1, LineKind::Definition(definitions.len()));
+            definitions.push(Definition {
+                key: MAIN_BLOCK.to_string(),
+                name: MAIN_BLOCK.to_string(),
+                owner: None,
+                line: i - 1,
+                col: column(line, MAIN_BLOCK),
+                signature: trimmed.trim_end().to_string(),
+                is_test: false,
+                is_macro: false,
+                is_assigned: false,
+                decorator: None,
+                is_declaration: false,
+                visibility: Visibility::Public,
+            });
+            pending_test = false;
+            pending_decorator = None;
+            continue;
+        }
+
+        // Only top-level assignments: inside a function they are locals, not graph nodes.
+        if leading_indent(line) == 0
+            && let Some(name) = lang.lambda_keywords.iter().find_map(|keyword| {
+                assigned_name(trimmed, keyword, &["="], |c| lang.is_ident_char(c), lang.is_valid_identifier)
+            })
+        {
+            kinds.mark(i - 1, LineKind::Definition(definitions.len()));
+            definitions.push(Definition {
+                key: name.clone(),
+                visibility: (lang.visibility)(trimmed, &name),
+                is_test: lang.test_name_prefixes.iter().any(|prefix| name.starts_with(prefix.as_str())),
+                col: column(line, &name),
+                name,
+                owner: None,
+                line: i - 1,
+                signature: trimmed.trim_end().to_string(),
+                is_macro: false,
+                is_assigned: true,
+                decorator: None,
+                is_declaration: false,
+            });
+            pending_test = false;
+            continue;
+        }
+
+        let name = if lang.top_level_only && leading_indent(line) > 0 {
+            None
+        } else {
+            (lang.extract_name)(lang, trimmed)
+        };
+        // A trailing `;` marks a declaration (`sub name;`), which is fine to skip silently.
+        if name.is_none() && leading_keyword(trimmed, &lang.func_defs).is_some() && !trimmed.trim_end().ends_with(';') {
+            crate::info!("Warning: Could not parse function name from line {}: {}", i, trimmed);
+        }
+
+        let Some(name) = name else {
+            let is_annotation = lang.annotation_prefixes.iter().any(|prefix| trimmed.starts_with(prefix.as_str()));
+            if is_annotation {
+                // Stacked decorators keep the kind listed first in the spec (`staticmethod` over `abstractmethod`).
+                if let Some(kind) = decorator_kind(lang, trimmed)
+                    && pending_decorator.as_ref().is_none_or(|pending| kind_rank(lang, &kind) < kind_rank(lang, pending))
+                {
+                    pending_decorator = Some(kind);
+                }
+            } else if !trimmed.is_empty() {
+                pending_decorator = None;
+            }
+            if !trimmed.is_empty() && !is_annotation {
+                let opens_block = trimmed.trim_end().ends_with(lang.end_def.as_str());
+                let test_header = lang.test_block_prefixes.iter().any(|prefix| trimmed.starts_with(prefix.as_str()));
+                if test_block.is_none() && opens_block && (pending_test || test_header) {
+                    test_block = Some(level);
+                }
+                pending_test = false;
+            }
+            if scope_closed && !trimmed.is_empty() {
+                scope_closed = false;
+                kinds.mark(i - 1, LineKind::ScopeBreak);
+                continue;
+            }
+            if let Some(depth) = &mut block_depth {
+                *depth += block_delta(lang, line);
+                if *depth <= 0 {
+                    block_depth = None;
+                    scope_closed = true;
+                }
+            }
+            kinds.mark(i - 1, LineKind::Body);
+            continue;
+        };
+
+        let def_line = i - 1;
+        kinds.mark(def_line, LineKind::Definition(definitions.len()));
+        let mut complete_def = line.to_string();
+        while !signature_complete(lang, &complete_def) {
+            let Some(next) = pieces.next() else {
+                break;
+            };
+            let next = next?;
+            complete_def.push(' ');
+            complete_def.push_str(next.text.trim());
+            if lang.brace_scopes {
+                braces.scan(lang, &next.text);
+            }
+            kinds.mark(i, LineKind::Signature);
+            i += 1;
+        }
+        // Selectors go on across the signature's lines (`- (void)move:(int)x` / `to:(int)y {`).
+        let name = if lang.message_calls && i > def_line + 1 {
+            (lang.extract_name)(lang, complete_def.trim()).unwrap_or(name)
+        } else {
+            name
+        };
+
+        if lang.block_end.is_some() {
+            let depth = block_delta(lang, &complete_def);
+            // A one-liner (`def name, do: value`) has no body to track.
+            block_depth = (depth > 0).then_some(depth);
+            scope_closed = depth <= 0;
+        }
+
+        let mut key = match &current_impl {
+            Some(block) => block.qualify(&name),
+            None => name.clone(),
+        };
+        if file_module.is_some() || !modules.is_empty() {
+            let mut path: Vec<&str> = file_module.iter().chain(modules.iter().map(|(module, _)| module)).map(String::as_str).collect();
+            path.push(&key);
+            key = path.join(&lang.module_separator);
+        }
+        // Trait members take the trait's visibility; implementing a trait exposes the method with it.
+        let visibility = match (&trait_block, &current_impl) {
+            (Some((_, visibility)), _) => *visibility,
+            (None, Some(block)) if block.trait_name.is_some() => Visibility::Public,
+            _ => (lang.visibility)(trimmed, &name),
+        };
+        definitions.push(Definition {
+            key,
+            visibility,
+            is_test: pending_test
+                || test_block.is_some()
+                || lang.test_name_prefixes.iter().any(|prefix| name.starts_with(prefix.as_str())),
+            owner: current_impl.clone(),
+            line: def_line,
+            col: column(line, &name),
+            signature: normalize_signature(lang, &complete_def),
+            name,
+            is_macro: false,
+            is_assigned: false,
+            decorator: pending_decorator.take(),
+            is_declaration: is_declaration(lang, &complete_def),
+        });
+        pending_test = false;
+    }
+
+    Ok((definitions, kinds))
+}
+
+/// Whether `trimmed` is one of the language's entrypoint guards, however it is spaced and
+/// whatever comment follows it.
+fn is_main_guard(lang: &LangConfig, trimmed: &str) -> bool {
+    if lang.main_guards.is_empty() {
+        return false;
+    }
+    let code = lang.line_comments.iter().filter_map(|comment| trimmed.find(comment.as_str())).min().map_or(trimmed, |pos| &trimmed[..pos]);
+    let squeezed: String = code.split_whitespace().collect();
+    lang.main_guards.iter().any(|guard| guard.split_whitespace().collect::<String>() == squeezed)
+}
+
+/// Visibility of a `trait Name {` header line, or `None` for any other line.
+fn trait_header(lang: &LangConfig, trimmed: &str) -> Option<Visibility> {
+    let rest = match trimmed.strip_prefix("pub") {
+        Some(rest) if rest.starts_with('(') => &rest[rest.find(')')? + 1..],
+        Some(rest) => rest,
+        None => trimmed,
+    };
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix("unsafe ").unwrap_or(rest).trim_start();
+    (rest.starts_with("trait ") && trimmed.trim_end().ends_with('{')).then(|| (lang.visibility)(trimmed, ""))
+}
+
+/// Rejects a file with a line over `--max-line-length` characters, which the per-line scans would
+/// crawl through: such files are minified or generated rather than written.
+/// `n` is the line's 0-based number.
+fn check_line_length(n: usize, line: &str, max_line_length: usize) -> Result<(), ParseError> {
+    // Bytes bound characters from above, so only lines this long need counting.
+    if line.len() <= max_line_length {
+        return Ok(());
+    }
+    let chars = line.chars().count();
+    if chars > max_line_length {
+        return Err(ParseError::LineTooLong(format!(
+            "line {} has {} characters, over the limit of {} (--max-line-length); \
+             the file is probably minified or generated",
+            n + 1,
+            chars,
+            max_line_length
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a parse that found more than `--max-functions` definitions before edges are matched.
+fn check_function_count(count: usize, config: &Config) -> Result<(), ParseError> {
+    if count > config.max_functions {
+        return Err(ParseError::TooManyFunctions(format!(
+            "found {} definitions, over the limit of {} (--max-functions); \
+             the language was probably detected wrongly or a custom keyword matches too much",
+            count, config.max_functions
+        )));
+    }
+    Ok(())
+}
+
+pub fn parse_functions(lang: &LangConfig, config: &Config, content: &str) -> Result<HashMap<String, FnInfo>, ParseError> {
+    parse_source_lines(lang, config, Source::Text(content))
+}
 
-    if let Some(paren_pos) = after_def.find(L::PARAMS_OPEN) {
-        let name = after_def[..paren_pos].trim();
-        if !name.is_empty() && L::is_valid_identifier(name) {
-            return Some(name.to_string());
-        }
+/// [`parse_functions`] over any [`Source`]. Each pass walks the lines afresh, so only what the
+/// first one gathered (definitions, imports, line kinds and where lines were cut) stays in memory.
+fn parse_source_lines(lang: &LangConfig, config: &Config, source: Source) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let mut modules: Vec<String> = Vec::new();
+    let mut pieces = Pieces::find(lang, source.lines()?, config.max_line_length);
+    let (mut definitions, kinds) = collect_definitions(
+        lang,
+        config,
+        pieces.by_ref().inspect(|piece| {
+            if let Ok(piece) = piece {
+                note_imports(lang, &piece.text, &mut modules);
+            }
+        }),
+    )?;
+    if pieces.piece == 0 {
+        return Err(ParseError::ParseFailure("File is empty".to_string()));
     }
-    None
-}
+    let splits = pieces.found;
 
-fn line_contains_function_call(line: &str, func_name: &str) -> bool {
-    if !line.contains(func_name) {
-        return false;
+    check_function_count(definitions.len(), config)?;
+    // Calls outside every definition (a script's top-level code) are made by `<module>`, which
+    // is only kept if it makes some.
+    let module_idx = (config.module_node && lang.top_level_code).then(|| {
+        definitions.push(Definition {
+            key: MODULE_BLOCK.to_string(),
+            name: MODULE_BLOCK.to_string(),
+            owner: None,
+            line: 0,
+            col: 0,
+            signature: String::new(),
+            is_test: false,
+            is_macro: false,
+            is_assigned: false,
+            decorator: None,
+            is_declaration: false,
+            visibility: Visibility::Public,
+        });
+        definitions.len() - 1
+    });
+    if crate::log::enabled(crate::log::DEBUG) {
+        for (i, piece) in Pieces::replay(source.lines()?, &splits).enumerate() {
+            let piece = piece?;
+            let decision = match kinds.of(lang, i, &piece.text) {
+                LineKind::Definition(idx) => format!("definition of {}", definitions[idx].key),
+                LineKind::Skip => "skip (comment)".to_string(),
+                LineKind::Signature => "signature continuation".to_string(),
+                LineKind::ScopeBreak => "scope break".to_string(),
+                LineKind::Body => "body".to_string(),
+            };
+            crate::debug!("line {}: {}", piece.line + 1, decision);
+        }
     }
-    
-    let pattern = format!("{}(", func_name);
-    if line.contains(&pattern) {
-        return true;
+    let ambiguous: Vec<bool> = definitions
+        .iter()
+        .map(|def| def.owner.is_some() && definitions.iter().filter(|other| other.name == def.name).count() > 1)
+        .collect();
+    // With `--include-macros`, `name!(..)` falls back to a function `name` when no such macro is defined.
+    let macro_fallback: Vec<bool> = definitions
+        .iter()
+        .map(|def| {
+            config.include_macros
+                && lang.macro_def.is_some()
+                && !def.is_macro
+                && def.owner.is_none()
+                && !definitions.iter().any(|other| other.is_macro && other.name == def.name)
+        })
+        .collect();
+
+    let excluded = |def: &Definition| config.exclude_tests && def.is_test;
+
+    let mut functions: HashMap<String, FnInfo> = HashMap::new();
+    for def in definitions.iter().filter(|def| !excluded(def)) {
+        // Multi-clause functions (`fact 0 = 1`, `fact n = ..`) keep their first clause's line.
+        functions.entry(def.key.clone()).or_insert_with(|| FnInfo {
+            line_at_call: def.line,
+            callees: Vec::new(),
+            signature: if config.edges_only { String::new() } else { def.signature.clone() },
+            end_line: def.line,
+            source_file: None,
+            def_col: def.col,
+            call_cols: HashMap::new(),
+            is_external: false,
+            is_method: takes_self(lang, &def.signature),
+            is_associated: def.owner.is_some() && !takes_self(lang, &def.signature),
+            is_recursive: false,
+            references: HashSet::new(),
+            decorator: def.decorator.clone(),
+            is_declaration: def.is_declaration,
+            visibility: def.visibility,
+            raw_names: Vec::new(),
+        });
     }
-    
-    let method_pattern = format!(".{}(", func_name);
-    line.contains(&method_pattern)
-}
 
-pub fn parse_functions(file_info: &FileInfo, content: &str) -> Result<HashMap<String, FnInfo>, ParseError> {
-    use crate::lang::{py::Python, rs::Rust};
-    
-    let (func_def, params_open, _params_close, _end_def) = match file_info.file_type {
-        Language::Py => (
-            Python::FUNC_DEF,
-            Python::PARAMS_OPEN,
-            Python::PARAMS_CLOSE,
-            Python::END_DEF,
-        ),
-        Language::Rs => (
-            Rust::FUNC_DEF,
-            Rust::PARAMS_OPEN,
-            Rust::PARAMS_CLOSE,
-            Rust::END_DEF,
-        ),
-        Language::Unknown => {
-            return Err(ParseError::UnsupportedLanguage("unknown".into()));
+    // Calls are matched against defined names (and their impl types) as spelled at the definition.
+    let names = config.ignore_case_calls.then(|| {
+        case_folded(definitions.iter().flat_map(|def| {
+            let owner = def.owner.iter().flat_map(|owner| std::iter::once(&owner.self_ty).chain(&owner.trait_name));
+            std::iter::once(&def.name).chain(owner).map(String::as_str)
+        }))
+    });
+    if let Some(names) = &names {
+        // Module names are single words, so respelling them is respelling their import lines.
+        let mut respelled = Vec::new();
+        for module in modules {
+            let module = respell_names(lang, &module, names).into_owned();
+            if !respelled.contains(&module) {
+                respelled.push(module);
+            }
         }
-    };
-    
-    let mut functions = HashMap::new();
-    let mut fn_names = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
-    
-    if lines.is_empty() {
-        return Err(ParseError::ParseFailure("File is empty".to_string()));
+        modules = respelled;
     }
-    
-    let mut current_fn: Option<String> = None;
-    let mut i = 0;
-    
-    while i < lines.len() {
-        let line = lines[i];
-        let trimmed = line.trim_start();
-        
-        if trimmed.starts_with(func_def) {
-            let fn_name = match file_info.file_type {
-                Language::Py => extract_function_name::<Python>(trimmed),
-                Language::Rs => extract_function_name::<Rust>(trimmed),
-                Language::Unknown => None,
-            };
-            
-            if let Some(name) = fn_name {
-                let mut complete_def = line.to_string();
-                let mut line_idx = i;
-                
-                while !complete_def.trim_end().ends_with(params_open) && line_idx + 1 < lines.len() {
-                    line_idx += 1;
-                    complete_def.push(' ');
-                    complete_def.push_str(lines[line_idx].trim());
-                }
-                
-                functions.insert(
-                    name.clone(),
-                    FnInfo {
-                        line_at_call: i,
-                        callees: Vec::new(),
+
+    let mut walker = BodyWalker::default();
+    // Locals bound so far in the current function, for `--definition-only-edges`.
+    let mut shadowed: Vec<String> = Vec::new();
+    // Calls through imported modules (`utils.helper(`), recorded under `--include-external`.
+    let mut external: Vec<(String, usize)> = Vec::new();
+    // Module-level dispatch tables (`HANDLERS = {"a": func_a}`) and the functions they hold, and
+    // the table being filled now, for `--include-references`.
+    let mut tables: Vec<(String, Vec<String>)> = Vec::new();
+    let mut table: Option<usize> = None;
+    for (i, piece) in Pieces::replay(source.lines()?, &splits).enumerate() {
+        let piece = piece?;
+        let kind = kinds.of(lang, i, &piece.text);
+        let line = match &names {
+            Some(names) => respell_names(lang, &piece.text, names),
+            None => Cow::Borrowed(piece.text.as_ref()),
+        };
+        let line = line.as_ref();
+        let indent = leading_indent(line);
+        if let LineKind::Definition(_) = kind {
+            shadowed.clear();
+        }
+        let Some((scope_idx, code)) = walker.step(lang, &definitions, kind, line) else {
+            continue;
+        };
+        if scope_idx.is_none() {
+            if config.include_references && matches!(kind, LineKind::Body) {
+                let trimmed = code.trim_start();
+                if let Some(name) = local_binding(lang, trimmed) {
+                    tables.push((name.to_string(), Vec::new()));
+                    table = Some(tables.len() - 1);
+                } else if indent == 0 && !trimmed.is_empty() {
+                    // Back at the margin without a new binding (a closing `}` or a statement).
+                    table = None;
+                }
+                if let Some(t) = table {
+                    for def in definitions.iter().filter(|def| def.owner.is_none() && !def.is_macro && !excluded(def)) {
+                        if function_reference(lang, code, &def.name).is_some() && !tables[t].1.contains(&def.key) {
+                            tables[t].1.push(def.key.clone());
+                        }
                     }
-                );
-                fn_names.push(name.clone());
-                current_fn = Some(name);
-                i = line_idx;
-            } else {
-                eprintln!("Warning: Could not parse function name from line {}: {}", i + 1, trimmed);
+                }
+            }
+        } else {
+            table = None;
+        }
+        let Some(current_idx) = scope_idx.or(module_idx.filter(|_| matches!(kind, LineKind::Body))) else {
+            continue;
+        };
+
+        let current = &definitions[current_idx];
+        if excluded(current) {
+            continue;
+        }
+        if !code.trim().is_empty()
+            && let Some(info) = functions.get_mut(&current.key)
+        {
+            info.end_line = info.end_line.max(i);
+        }
+        // Locals also never count as references to a function of the same name.
+        if (config.definition_only_edges || config.include_references)
+            && let Some(local) = local_binding(lang, code.trim_start())
+        {
+            shadowed.push(local.to_string());
+        }
+        let offset = code.as_ptr().addr() - line.as_ptr().addr();
+        let (code, module_calls) = mask_module_calls(lang, code, &modules);
+        let code = code.as_ref();
+        if config.include_external {
+            for (callee, pos) in module_calls {
+                // `os.path.join` is stdlib noise unless asked for; it stays masked either way.
+                let module = callee.split('.').next().unwrap_or_default();
+                if !config.show_stdlib && lang.builtins.iter().any(|builtin| builtin == module) {
+                    continue;
+                }
+                if let Some(info) = functions.get_mut(&current.key)
+                    && !info.callees.iter().any(|(name, _)| *name == callee)
+                {
+                    crate::debug!("line {}: external edge {} -> {}", i + 1, current.key, callee);
+                    info.callees.push((callee.clone(), i));
+                    if !config.edges_only {
+                        info.call_cols.insert(callee.clone(), line[..offset + pos].chars().count());
+                    }
+                    external.push((callee, i));
+                }
+            }
+        }
+        for ((def, &ambiguous), &macro_fallback) in definitions.iter().zip(&ambiguous).zip(&macro_fallback) {
+            if config.definition_only_edges && def.owner.is_none() && !def.is_macro && shadowed.contains(&def.name) {
+                continue;
             }
-        } else if let Some(ref current_func) = current_fn {
-            if !line.is_empty() && !line.starts_with(' ') && !line.starts_with('\t') {
-                current_fn = None;
+            // A function only calls itself through its own path (`Self::name(`, `self.name(`, bare
+            // `name(`): `self.inner.fmt(f)` or `net::connect()` reach a different function.
+            let recursive = def.key == current.key;
+            let calls = if recursive && def.owner.is_none() && !def.is_macro {
+                line_contains_own_call(lang, code, &def.name)
             } else {
-                for func_name in &fn_names {
-                    if func_name != current_func && line_contains_function_call(line, func_name) {
-                        if let Some(info) = functions.get_mut(current_func) {
-                            if !info.callees.iter().any(|(name, _)| name == func_name) {
-                                info.callees.push((func_name.clone(), i));
-                            }
-                        }
+                line_calls_definition(lang, code, def, current.owner.as_ref(), ambiguous || recursive)
+                    || (macro_fallback && line_contains_macro_call(code, &def.name))
+            };
+            if !excluded(def)
+                && calls
+                && let Some(info) = functions.get_mut(&current.key)
+                // A call after a bare mention turns the reference edge into a call edge.
+                && (info.references.remove(&def.key) || !info.callees.iter().any(|(name, _)| *name == def.key))
+            {
+                crate::debug!("line {}: edge {} -> {}", i + 1, current.key, def.key);
+                info.is_recursive |= recursive;
+                match info.callees.iter_mut().find(|(name, _)| *name == def.key) {
+                    Some(edge) => edge.1 = i,
+                    None => info.callees.push((def.key.clone(), i)),
+                }
+                if !config.edges_only {
+                    info.call_cols.insert(def.key.clone(), name_column(lang, line, offset, code, &def.name));
+                }
+            } else if config.include_references
+                && !calls
+                && !recursive
+                && !excluded(def)
+                && def.owner.is_none()
+                && !def.is_macro
+                && !lang.bare_calls
+                && !shadowed.contains(&def.name)
+                && let Some(pos) = function_reference(lang, code, &def.name)
+                && let Some(info) = functions.get_mut(&current.key)
+                && !info.callees.iter().any(|(name, _)| *name == def.key)
+            {
+                crate::debug!("line {}: reference edge {} -> {}", i + 1, current.key, def.key);
+                info.callees.push((def.key.clone(), i));
+                if !config.edges_only {
+                    info.call_cols.insert(def.key.clone(), line[..offset + pos].chars().count());
+                }
+                info.references.insert(def.key.clone());
+            }
+        }
+    }
+
+    // A function using a dispatch table may call any function in it, which takes another pass
+    // now that the tables are known.
+    tables.retain(|(_, targets)| !targets.is_empty());
+    if !tables.is_empty() {
+        let mut walker = BodyWalker::default();
+        for (i, piece) in Pieces::replay(source.lines()?, &splits).enumerate() {
+            let piece = piece?;
+            let kind = kinds.of(lang, i, &piece.text);
+            let line = match &names {
+                Some(names) => respell_names(lang, &piece.text, names),
+                None => Cow::Borrowed(piece.text.as_ref()),
+            };
+            let line = line.as_ref();
+            let Some((scope_idx, code)) = walker.step(lang, &definitions, kind, line) else {
+                continue;
+            };
+            let Some(current_idx) = scope_idx.or(module_idx.filter(|_| matches!(kind, LineKind::Body))) else {
+                continue;
+            };
+            let current = &definitions[current_idx];
+            if excluded(current) {
+                continue;
+            }
+            for (name, targets) in &tables {
+                let Some(pos) = find_token(lang, code, name) else {
+                    continue;
+                };
+                let Some(info) = functions.get_mut(&current.key) else {
+                    continue;
+                };
+                for target in targets.iter().filter(|target| **target != current.key) {
+                    if info.callees.iter().any(|(callee, _)| callee == target) {
+                        continue;
+                    }
+                    crate::debug!("line {}: reference edge {} -> {} through {}", i + 1, current.key, target, name);
+                    info.callees.push((target.clone(), i));
+                    if !config.edges_only {
+                        let offset = code.as_ptr().addr() - line.as_ptr().addr();
+                        info.call_cols.insert(target.clone(), line[..offset + pos].chars().count());
                     }
+                    info.references.insert(target.clone());
                 }
             }
         }
-        
-        i += 1;
     }
-    
+
+    if functions.get(MODULE_BLOCK).is_some_and(|info| info.callees.is_empty()) {
+        functions.remove(MODULE_BLOCK);
+    }
+    for (callee, line) in external {
+        functions.entry(callee).or_insert_with(|| FnInfo {
+            line_at_call: line,
+            end_line: line,
+            is_external: true,
+            ..FnInfo::default()
+        });
+    }
+
+    if !splits.is_empty() {
+        restore_positions(&mut functions, &splits);
+    }
     Ok(functions)
 }
 
+/// Follows which definition's body each line of a replayed pass is in.
+///
+/// Open definitions are kept innermost last. A definition's body runs until a line indented no
+/// deeper than its header, so functions nested in `if`/`try` blocks or in other functions are
+/// scoped by their own indentation, and the enclosing function resumes afterwards. Brace-scoped
+/// languages instead end a body when its braces balance, wherever the closing `}` is indented.
+#[derive(Default)]
+struct BodyWalker {
+    scopes: Vec<Scope>,
+    braces: BraceCounter,
+}
+
+impl BodyWalker {
+    /// Moves past `line`, of kind `kind`. Returns the innermost open definition and the part of
+    /// the line that can make calls, or `None` for lines that make none (comments, headers).
+    fn step<'l>(&mut self, lang: &LangConfig, definitions: &[Definition], kind: LineKind, line: &'l str) -> Option<(Option<usize>, &'l str)> {
+        let indent = leading_indent(line);
+        let scopes = &mut self.scopes;
+        if lang.brace_scopes {
+            while scopes.last().is_some_and(|scope| scope.closed) {
+                scopes.pop();
+            }
+            let start = self.braces.depth;
+            let peak = self.braces.scan(lang, line);
+            if let LineKind::Definition(idx) = kind {
+                // A header whose body never opened was a declaration (`fn name();`).
+                while scopes.last().is_some_and(|scope| !scope.opened) {
+                    scopes.pop();
+                }
+                scopes.push(Scope { idx, indent, depth: start, opened: false, closed: false });
+            }
+            // An expression body (`=> expr;`) never opens braces and ends at its `;`.
+            let statement_end = line.trim_end().ends_with(';');
+            for scope in scopes.iter_mut() {
+                scope.opened |= peak > scope.depth;
+                scope.closed |= (scope.opened || statement_end) && self.braces.depth <= scope.depth;
+            }
+        }
+        let code = match kind {
+            LineKind::Definition(idx) => {
+                if !lang.brace_scopes {
+                    while scopes.last().is_some_and(|scope| scope.indent >= indent) {
+                        scopes.pop();
+                    }
+                    scopes.push(Scope { idx, indent, depth: 0, opened: true, closed: false });
+                }
+                // One-liners (`def name, do: other()`) carry their body on the definition line.
+                if definitions[idx].is_assigned { Some(line) } else { definition_code(lang, line) }?
+            }
+            // Parameter defaults on a continuation line are evaluated like the rest of the signature.
+            LineKind::Signature if lang.default_args => line,
+            LineKind::Skip | LineKind::Signature => return None,
+            LineKind::ScopeBreak => {
+                if !lang.brace_scopes {
+                    scopes.clear();
+                }
+                return None;
+            }
+            LineKind::Body => {
+                if !lang.brace_scopes && !line.trim().is_empty() {
+                    while scopes.last().is_some_and(|scope| scope.indent >= indent) {
+                        scopes.pop();
+                    }
+                }
+                line
+            }
+        };
+        Some((scopes.last().map(|scope| scope.idx), code))
+    }
+}
+
+/// For `--ignore-case-calls`: respells each word of `line` that equals one of `names` ignoring
+/// ASCII case as that name, so `HELPER()` reads as a call to `helper`. Byte positions don't move.
+fn respell_names<'a>(lang: &LangConfig, line: &'a str, names: &HashMap<String, Option<&str>>) -> Cow<'a, str> {
+    let mut respelled = Cow::Borrowed(line);
+    let mut start = None;
+    for (pos, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        match (lang.is_ident_char(c), start) {
+            (true, None) => start = Some(pos),
+            (false, Some(from)) => {
+                start = None;
+                let word = &line[from..pos];
+                if let Some(Some(name)) = names.get(&word.to_ascii_lowercase())
+                    && *name != word
+                {
+                    respelled.to_mut().replace_range(from..pos, name);
+                }
+            }
+            _ => {}
+        }
+    }
+    respelled
+}
+
+/// The spelling [`respell_names`] gives each name, by its lowercase form. Names differing only in
+/// case (`Run` and `run`) map to `None`: which one a call means is left to its exact spelling.
+fn case_folded<'a>(names: impl IntoIterator<Item = &'a str>) -> HashMap<String, Option<&'a str>> {
+    let mut folded: HashMap<String, Option<&str>> = HashMap::new();
+    for name in names {
+        folded
+            .entry(name.to_ascii_lowercase())
+            .and_modify(|known| {
+                if known.is_some_and(|known| known != name) {
+                    *known = None;
+                }
+            })
+            .or_insert(Some(name));
+    }
+    folded
+}
+
+/// Byte offsets where a definition after the line's first one starts (`fn b` in
+/// `fn a() {} pub fn b() {}`), including its leading modifiers. Keywords inside a string or
+/// after a line comment don't count.
+fn later_definitions(lang: &LangConfig, line: &str) -> Vec<usize> {
+    let first = line.len() - line.trim_start().len();
+    let mut starts: Vec<usize> = lang
+        .func_defs
+        .iter()
+        .flat_map(|keyword| line.match_indices(keyword.as_str()).map(|(pos, _)| pos))
+        .filter(|&pos| pos > first && !line[..pos].ends_with(|c: char| lang.is_ident_char(c)))
+        .filter(|&pos| {
+            let before = &line[..pos];
+            before.matches('"').count().is_multiple_of(2) && !lang.line_comments.iter().any(|comment| before.contains(comment.as_str()))
+        })
+        .filter_map(|pos| {
+            // Back over the modifiers (`pub`, `async`) that belong to this definition.
+            let mut start = pos;
+            loop {
+                let head = line[..start].trim_end();
+                let word_start = head.rfind(|c: char| c.is_whitespace() || "{};".contains(c)).map_or(0, |p| p + 1);
+                if word_start == head.len() || !lang.modifiers.iter().any(|modifier| *modifier == head[word_start..]) {
+                    break;
+                }
+                start = word_start;
+            }
+            (start > first && (lang.extract_name)(lang, &line[start..]).is_some()).then_some(start)
+        })
+        .collect();
+    starts.sort();
+    starts.dedup();
+    starts
+}
+
+/// Maps the line numbers and columns of a parse over [`Pieces`] back onto the file.
+fn restore_positions(functions: &mut HashMap<String, FnInfo>, splits: &[Split]) {
+    for info in functions.values_mut() {
+        for (callee, line) in &mut info.callees {
+            let (origin, shift) = origin(splits, *line);
+            if let Some(col) = info.call_cols.get_mut(callee) {
+                *col += shift;
+            }
+            *line = origin;
+        }
+        let (origin_line, shift) = origin(splits, info.line_at_call);
+        info.def_col += shift;
+        info.line_at_call = origin_line;
+        info.end_line = origin(splits, info.end_line).0;
+    }
+}
+
 pub fn parse_file(file_info: &FileInfo, config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
-    let file_content = read_file(&file_info.file_path)?;
-    
-    if file_content.is_empty() {
+    if file_info.file_size > config.max_file_size {
+        return Err(ParseError::FileTooLarge(format!(
+            "{} is {} bytes, over the {} byte limit (--max-file-size)",
+            file_info.file_path.display(),
+            file_info.file_size,
+            config.max_file_size
+        )));
+    }
+    // A mapped file is already off the heap; otherwise each pass reads the file through a buffer.
+    let mapped = if config.mmap { Some(read_source(file_info.file_path, true)?) } else { None };
+    let source = match &mapped {
+        Some(content) => Source::Text(content),
+        None => Source::File(file_info.file_path),
+    };
+
+    if mapped.as_ref().map_or(file_info.file_size == 0, |content| content.is_empty()) {
         return Err(ParseError::ParseFailure("File is empty".to_string()));
     }
     
-    if config.enable_cache {
-        match cache::load_cache(&file_info.file_path, &file_content) {
-            Ok(Some(cached_functions)) => return Ok(cached_functions),
+    // Cache entries don't record which spec produced them, so overridden specs always re-parse.
+    let use_cache = config.enable_cache
+        && config.def_keyword.is_none()
+        && config.body_open.is_none()
+        && config.custom_langs.is_empty();
+
+    let file_hash = match source {
+        _ if !use_cache => None,
+        Source::Text(content) => Some(cache::hash_string(content)),
+        Source::File(path) => Some(cache::hash_file(path)?),
+    };
+    if let Some(file_hash) = file_hash {
+        match cache::load_cache(file_info.file_path, file_hash, config.cache_key(), config.cache_ttl) {
+            Ok(Some(cached_functions)) => {
+                for (n, line) in source.lines()?.enumerate() {
+                    check_line_length(n, &line?, config.max_line_length)?;
+                }
+                check_function_count(cached_functions.len(), config)?;
+                return Ok(with_source_file(cached_functions, file_info.file_path));
+            }
             Ok(None) => {},
             Err(e) => {
                 eprintln!("Cache error (continuing without cache): {}", e);
@@ -141,13 +1901,652 @@ pub fn parse_file(file_info: &FileInfo, config: &Config) -> Result<HashMap<Strin
         }
     }
     
-    let functions = parse_functions(file_info, &file_content)?;
+    let lang = lang_config(file_info, config)?;
+    let functions = parse_source_lines(&lang, config, source)?;
     
-    if config.enable_cache {
-        if let Err(e) = cache::save_cache(&file_info.file_path, &file_content, &functions) {
-            eprintln!("Failed to save cache (continuing): {}", e);
-        }
+    if let Some(file_hash) = file_hash
+        && let Err(e) = cache::save_cache(file_info.file_path, file_hash, config.cache_key(), &functions, config.json_pretty)
+    {
+        eprintln!("Failed to save cache (continuing): {}", e);
     }
     
-    Ok(functions)
+    Ok(with_source_file(functions, file_info.file_path))
+}
+
+/// Records `path` as the defining file of every non-external function; cache entries are stored without it,
+/// so they stay valid if the file is reached by a different path.
+fn with_source_file(mut functions: HashMap<String, FnInfo>, path: &Path) -> HashMap<String, FnInfo> {
+    for info in functions.values_mut().filter(|info| !info.is_external) {
+        info.source_file = Some(path.to_path_buf());
+    }
+    functions
+}
+
+/// Parses in-memory `source` (piped input) as if it were the file `label`: the language, custom
+/// ones included, comes from the label's extension, and functions record it as their `source_file`.
+pub fn parse_labeled(source: &str, label: &PathBuf, config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let file_info = FileInfo {
+        file_type: Language::from_path(label),
+        file_path: label,
+        file_size: source.len(),
+    };
+    let lang = lang_config(&file_info, config)?;
+    let functions = parse_functions(&lang, config, source)?;
+    Ok(with_source_file(functions, label))
+}
+
+/// Parses in-memory `source` as `language`; functions get no `source_file`.
+pub fn parse_source(source: &str, language: &Language, config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let lang = LangConfig::try_from(language)?;
+    parse_functions(&lang, config, source)
+}
+
+type ParseResult = Result<HashMap<String, FnInfo>, ParseError>;
+
+fn is_supported(path: &Path, config: &Config) -> bool {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let custom = config
+        .custom_langs
+        .iter()
+        .any(|lang| extension.is_some_and(|ext| lang.extensions.iter().any(|e| e == ext)));
+
+    custom || extension.is_some_and(|ext| Language::ALL.iter().any(|language| language.extensions().contains(&ext)))
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, ParseError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| ParseError::InvalidGlob(format!("{}: {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| ParseError::InvalidGlob(e.to_string()))
+}
+
+/// Whether `relative` passes `config.include`/`config.exclude` (compiled as `include`/`exclude`).
+fn is_selected(relative: &Path, config: &Config, include: &GlobSet, exclude: &GlobSet) -> bool {
+    (config.include.is_empty() || include.is_match(relative)) && !exclude.is_match(relative)
+}
+
+/// Recursively lists supported source files under `dir`, skipping hidden entries, sorted by path.
+///
+/// `config.include`/`config.exclude` globs are matched against the path relative to `dir`;
+/// a file matching both is excluded.
+pub fn collect_source_files(dir: &Path, config: &Config) -> Result<Vec<PathBuf>, ParseError> {
+    let include = glob_set(&config.include)?;
+    let exclude = glob_set(&config.exclude)?;
+    let selected = |path: &Path| {
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        is_selected(relative, config, &include, &exclude)
+    };
+
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_supported(&path, config) && selected(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Renames `old` to `new` in one file's map, including that file's own references to it.
+fn rename_function(functions: &mut HashMap<String, FnInfo>, old: &str, new: &str) {
+    if let Some(info) = functions.remove(old) {
+        functions.insert(new.to_string(), info);
+    }
+    for info in functions.values_mut() {
+        for (callee, _) in &mut info.callees {
+            if callee == old {
+                *callee = new.to_string();
+            }
+        }
+        if let Some(col) = info.call_cols.remove(old) {
+            info.call_cols.insert(new.to_string(), col);
+        }
+        if info.references.remove(old) {
+            info.references.insert(new.to_string());
+        }
+    }
+}
+
+/// Merges per-file maps (in path order) according to `strategy`.
+fn merge_files(
+    dir: &Path,
+    mut parsed: Vec<(&PathBuf, HashMap<String, FnInfo>)>,
+    strategy: MergeStrategy,
+) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let mut defined_in: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for (path, functions) in &parsed {
+        // External functions aren't defined here, so every file calling one shares a single node.
+        for (name, _) in functions.iter().filter(|(_, info)| !info.is_external) {
+            defined_in.entry(name.clone()).or_default().push(path);
+        }
+    }
+
+    let mut duplicates: Vec<(&String, &Vec<&PathBuf>)> = defined_in.iter().filter(|(_, files)| files.len() > 1).collect();
+    duplicates.sort();
+    // Every script has its own top-level code, so those blocks are qualified whatever the strategy.
+    let (blocks, duplicates): (Vec<_>, Vec<_>) =
+        duplicates.into_iter().partition(|(name, _)| *name == MODULE_BLOCK || *name == MAIN_BLOCK);
+
+    let qualified = match strategy {
+        MergeStrategy::Error => {
+            if let Some((name, files)) = duplicates.first() {
+                return Err(ParseError::DuplicateName(format!(
+                    "`{}` is defined in {} and {}",
+                    name,
+                    files[0].display(),
+                    files[1].display()
+                )));
+            }
+            blocks
+        }
+        MergeStrategy::Qualify => blocks.into_iter().chain(duplicates).collect(),
+        MergeStrategy::First => blocks,
+    };
+    for (path, functions) in &mut parsed {
+        let relative = path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        for (name, _) in &qualified {
+            if functions.contains_key(*name) {
+                rename_function(functions, name, &format!("{}::{}", relative, name));
+            }
+        }
+    }
+
+    let mut merged = HashMap::new();
+    for (_, functions) in parsed {
+        for (name, info) in functions {
+            merged.entry(name).or_insert(info);
+        }
+    }
+    Ok(merged)
+}
+
+/// Parses every supported file under `dir` on `config.threads` workers and merges the results.
+///
+/// Files are merged in path order regardless of which worker finishes first, so names defined in
+/// several files resolve the same way on every run. Files that fail to parse are skipped with a warning.
+pub fn parse_directory(dir: &Path, config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let files = collect_source_files(dir, config)?;
+    parse_files(dir, &files, config)
+}
+
+/// Parses `files` (sorted, under `dir`) in parallel and merges them as [`parse_directory`] does.
+pub fn parse_files(dir: &Path, files: &[PathBuf], config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let next = AtomicUsize::new(0);
+    let workers = config.threads.clamp(1, files.len().max(1));
+
+    let mut results: Vec<(usize, ParseResult)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parsed = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(idx) else {
+                            break;
+                        };
+                        let result = FileInfo::from_path(path)
+                            .map_err(|e| ParseError::ParseFailure(e.to_string()))
+                            .and_then(|file_info| parse_file(&file_info, config));
+                        parsed.push((idx, result));
+                    }
+                    parsed
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("parser thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(idx, _)| *idx);
+
+    let mut parsed = Vec::new();
+    for (idx, result) in results {
+        match result {
+            Ok(functions) => parsed.push((&files[idx], functions)),
+            Err(e) => eprintln!("Skipping {}: {}", files[idx].display(), e),
+        }
+    }
+
+    let linked: Vec<&PathBuf> = parsed.iter().map(|(path, _)| *path).collect();
+    let mut merged = merge_files(dir, parsed, config.merge_strategy)?;
+    for path in linked {
+        let result = if config.mmap {
+            read_source(path, true).and_then(|content| link_file(dir, path, Source::Text(&content), &mut merged, config))
+        } else {
+            link_file(dir, path, Source::File(path), &mut merged, config)
+        };
+        if let Err(e) = result {
+            eprintln!("Could not link calls out of {}: {}", path.display(), e);
+        }
+    }
+    Ok(merged)
+}
+
+/// Lists the supported source files inside the archive at `path`, selected and skipped as
+/// [`collect_source_files`] does with the entry path standing for the path under the directory.
+pub fn collect_archive_files(path: &Path, config: &Config) -> Result<Vec<archive::Entry>, ParseError> {
+    let include = glob_set(&config.include)?;
+    let exclude = glob_set(&config.exclude)?;
+    archive::read_archive(path, config.max_file_size, |entry| {
+        let hidden = entry.iter().any(|part| part.to_str().is_some_and(|part| part.starts_with('.')));
+        !hidden && is_supported(entry, config) && is_selected(entry, config, &include, &exclude)
+    })
+}
+
+/// Parses archive `entries` in order and merges and links them as [`parse_files`] does; each
+/// function's `source_file` is its entry path. Entries that fail to parse are skipped with a warning.
+pub fn parse_archive_files(entries: &[archive::Entry], config: &Config) -> Result<HashMap<String, FnInfo>, ParseError> {
+    let mut sources = Vec::new();
+    let mut parsed = Vec::new();
+    for entry in entries {
+        let result = if entry.data.len() > config.max_file_size {
+            Err(ParseError::FileTooLarge(format!(
+                "{} bytes, over the {} byte limit (--max-file-size)",
+                entry.data.len(),
+                config.max_file_size
+            )))
+        } else if entry.data.is_empty() {
+            Err(ParseError::ParseFailure("File is empty".to_string()))
+        } else {
+            std::str::from_utf8(&entry.data)
+                .map_err(|e| ParseError::ParseFailure(format!("not UTF-8: {}", e)))
+                .and_then(|source| parse_labeled(source, &entry.path, config).map(|functions| (source, functions)))
+        };
+        match result {
+            Ok((source, functions)) => {
+                sources.push(source);
+                parsed.push((&entry.path, functions));
+            }
+            Err(e) => eprintln!("Skipping {}: {}", entry.path.display(), e),
+        }
+    }
+
+    let linked: Vec<&PathBuf> = parsed.iter().map(|(path, _)| *path).collect();
+    let mut merged = merge_files(Path::new(""), parsed, config.merge_strategy)?;
+    for (path, source) in linked.into_iter().zip(sources) {
+        if let Err(e) = link_file(Path::new(""), path, Source::Text(source), &mut merged, config) {
+            eprintln!("Could not link calls out of {}: {}", path.display(), e);
+        }
+    }
+    Ok(merged)
+}
+
+/// Adds edges for calls from the functions of `path` (whose text is read from `source`) to functions defined in other files of the same
+/// language, which the per-file parse can't see.
+///
+/// Only plainly named functions are linked to; methods and names qualified by the merge keep
+/// the edges their own file gave them. Each body line belongs to the innermost definition around
+/// it, found as the per-file parse found it; lines of a definition the merge dropped (or
+/// `--exclude-tests` left out) link nothing, and only lines outside every definition are `<module>`'s.
+fn link_file(dir: &Path, path: &PathBuf, source: Source, functions: &mut HashMap<String, FnInfo>, config: &Config) -> Result<(), ParseError> {
+    let file_info = FileInfo {
+        file_type: Language::from_path(path),
+        file_path: path,
+        file_size: match source {
+            Source::Text(content) => content.len(),
+            Source::File(path) => std::fs::metadata(path)?.len() as usize,
+        },
+    };
+    let lang = lang_config(&file_info, config)?;
+    let extension = path.extension();
+
+    let targets: Vec<String> = functions
+        .iter()
+        .filter(|(name, info)| {
+            info.source_file.as_ref().is_some_and(|file| file != path && file.extension() == extension)
+                && name.chars().all(|c| lang.is_ident_char(c))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    // This file's functions by the line they start on, under the names the merge kept them as.
+    let mut local: Vec<(usize, String)> = functions
+        .iter()
+        .filter(|(_, info)| info.source_file.as_ref() == Some(path))
+        .map(|(name, info)| (info.line_at_call, name.clone()))
+        .collect();
+    local.sort();
+    let is_kept = |name: &str, key: &str| name == key || name.strip_suffix(key).is_some_and(|prefix| prefix.ends_with("::"));
+
+    let mut pieces = Pieces::find(&lang, source.lines()?, config.max_line_length);
+    let (definitions, kinds) = collect_definitions(&lang, config, pieces.by_ref())?;
+    let splits = pieces.found;
+    let names = config.ignore_case_calls.then(|| case_folded(targets.iter().map(String::as_str)));
+
+    let mut walker = BodyWalker::default();
+    for (i, piece) in Pieces::replay(source.lines()?, &splits).enumerate() {
+        let piece = piece?;
+        let kind = kinds.of(&lang, i, &piece.text);
+        let line = match &names {
+            Some(names) => respell_names(&lang, &piece.text, names),
+            None => Cow::Borrowed(piece.text.as_ref()),
+        };
+        let line = line.as_ref();
+        let Some((scope_idx, code)) = walker.step(&lang, &definitions, kind, line) else {
+            continue;
+        };
+        let (key, start) = match scope_idx {
+            Some(idx) => (definitions[idx].key.as_str(), origin(&splits, definitions[idx].line).0),
+            None if matches!(kind, LineKind::Body) => (MODULE_BLOCK, 0),
+            None => continue,
+        };
+        let called: Vec<&String> = targets.iter().filter(|target| line_contains_function_call(&lang, code, target)).collect();
+        if called.is_empty() {
+            continue;
+        }
+        let (n, col) = (piece.line, piece.col);
+        let caller = match local.iter().find(|(line, name)| *line == start && is_kept(name, key)) {
+            Some((_, caller)) => caller.clone(),
+            // Top-level code calling only into other files made no `<module>` in its own.
+            None if key == MODULE_BLOCK && config.module_node && lang.top_level_code => {
+                let caller = add_module_block(dir, path, functions, n);
+                local.push((0, caller.clone()));
+                caller
+            }
+            None => continue,
+        };
+        let Some(info) = functions.get_mut(&caller) else {
+            continue;
+        };
+        for target in called {
+            if !info.callees.iter().any(|(name, _)| name == target) {
+                crate::debug!("{}:{}: edge {} -> {}", path.display(), n + 1, caller, target);
+                info.callees.push((target.clone(), n));
+                info.end_line = info.end_line.max(n);
+                if !config.edges_only {
+                    let offset = code.as_ptr().addr() - line.as_ptr().addr();
+                    info.call_cols.insert(target.clone(), col + name_column(&lang, line, offset, code, target));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds `path`'s `<module>` node, first line `n`, qualified as [`merge_files`] qualifies one that
+/// several files have (renaming the unqualified one it finds), and returns its name.
+fn add_module_block(dir: &Path, path: &Path, functions: &mut HashMap<String, FnInfo>, n: usize) -> String {
+    let qualified = |file: &Path| format!("{}::{}", file.strip_prefix(dir).unwrap_or(file).to_string_lossy().replace('\\', "/"), MODULE_BLOCK);
+    if let Some(other) = functions.get(MODULE_BLOCK).and_then(|info| info.source_file.clone()) {
+        rename_function(functions, MODULE_BLOCK, &qualified(&other));
+    }
+    let suffix = format!("::{}", MODULE_BLOCK);
+    let name = if functions.keys().any(|name| name.ends_with(&suffix)) { qualified(path) } else { MODULE_BLOCK.to_string() };
+    functions.insert(
+        name.clone(),
+        FnInfo {
+            end_line: n,
+            source_file: Some(path.to_path_buf()),
+            visibility: Visibility::Public,
+            ..FnInfo::default()
+        },
+    );
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Brace depth after scanning `lines` in `language`.
+    fn depth_after(language: &Language, lines: &[&str]) -> usize {
+        let lang = LangConfig::try_from(language).unwrap();
+        let mut braces = BraceCounter::default();
+        for line in lines {
+            braces.scan(&lang, line);
+        }
+        braces.depth
+    }
+
+    #[test]
+    fn rust_braces_skip_char_literals_and_raw_strings_but_not_lifetimes() {
+        let lines = [
+            "fn first<'a>(s: &'a str) -> char {",
+            "    let open = '{';",
+            r###"    let raw = r#"}"#;"###,
+            r###"    let quoted = r#"say "é" now {"#;"###,
+        ];
+        assert_eq!(depth_after(&Language::Rs, &lines), 1);
+    }
+
+    #[test]
+    fn single_quotes_elsewhere_are_plain_quotes() {
+        let dart = ["void main() {", "  print('it}s');", "  var r = 'a';"];
+        assert_eq!(depth_after(&Language::Dart, &dart), 1);
+        let objc = ["- (BOOL)isOpen:(char)c {", "    return c == '}' || c == '\\'';"];
+        assert_eq!(depth_after(&Language::Objc, &objc), 1);
+    }
+
+    fn callee_names(info: &FnInfo) -> Vec<&str> {
+        info.callees.iter().map(|(callee, _)| callee.as_str()).collect()
+    }
+
+    #[test]
+    fn top_level_calls_belong_to_module() {
+        let source = "def helper():\n    return 1\n\nprint(helper())\n";
+        let functions = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        assert_eq!(callee_names(&functions[MODULE_BLOCK]), ["helper"]);
+        assert!(functions["helper"].callees.is_empty());
+    }
+
+    #[test]
+    fn each_script_of_a_directory_keeps_its_own_module() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/two_scripts");
+        for merge_strategy in [MergeStrategy::Qualify, MergeStrategy::First, MergeStrategy::Error] {
+            let config = Config { merge_strategy, exclude_tests: true, ..Config::default() };
+            let functions = parse_directory(&dir, &config).unwrap();
+            assert_eq!(callee_names(&functions["a.py::<module>"]), ["helper"]);
+            assert_eq!(callee_names(&functions["b.py::<module>"]), ["helper"]);
+            // Only the dropped test calls `shared`; its body isn't top-level code.
+            let entrypoints = [MODULE_BLOCK.to_string()];
+            assert_eq!(crate::find_orphans(&functions, &entrypoints, |_| false), ["shared"]);
+        }
+    }
+
+    #[test]
+    fn byte_order_mark_is_not_part_of_the_first_line() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bom.py");
+        let file_info = FileInfo::from_path(&path).unwrap();
+        let from_file = parse_file(&file_info, &Config::default()).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let from_text = parse_source(&text, &Language::Py, &Config::default()).unwrap();
+        for functions in [&from_file, &from_text] {
+            assert_eq!(callee_names(&functions["main"]), ["helper"]);
+            assert_eq!((functions["main"].line_at_call, functions["main"].def_col), (0, 4));
+        }
+    }
+
+    #[test]
+    fn functions_defined_under_top_level_guards() {
+        let source = "\
+import sys
+
+if sys.version_info >= (3, 8):
+    def load():
+        return parse()
+else:
+    def load():
+        return legacy()
+
+def parse():
+    pass
+
+def legacy():
+    pass
+
+if __name__ == \"__main__\":
+    def run():
+        load()
+    run()
+";
+        let functions = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        let mut load = callee_names(&functions["load"]);
+        load.sort();
+        assert_eq!(load, ["legacy", "parse"]);
+        assert_eq!(callee_names(&functions["run"]), ["load"]);
+        assert_eq!(callee_names(&functions[MAIN_BLOCK]), ["run"]);
+        assert!(!functions.contains_key(MODULE_BLOCK));
+    }
+
+    #[test]
+    fn rust_where_clause_over_several_lines() {
+        let source = "\
+fn largest<T, F>(items: &[T], key: F) -> &T
+where
+    T: PartialOrd,
+    F: Fn(&T) -> u32,
+{
+    first(items)
+}
+
+fn first<T>(items: &[T]) -> &T {
+    &items[0]
+}
+";
+        let functions = parse_source(source, &Language::Rs, &Config::default()).unwrap();
+        assert_eq!(functions["largest"].signature, "fn largest<T, F>(items: &[T], key: F) -> &T where T: PartialOrd, F: Fn(&T) -> u32");
+        assert_eq!(callee_names(&functions["largest"]), ["first"]);
+        assert_eq!(functions["largest"].end_line, 6);
+    }
+
+    #[test]
+    fn self_recursion_is_recorded() {
+        let source = "def factorial(n):\n    return 1 if n <= 1 else n * factorial(n - 1)\n\nprint(factorial(5))\n";
+        let functions = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        assert!(functions["factorial"].is_recursive);
+        assert_eq!(callee_names(&functions["factorial"]), ["factorial"]);
+        assert!(!functions[MODULE_BLOCK].is_recursive);
+    }
+
+    #[test]
+    fn kind_decorators_are_recorded() {
+        let source = "\
+import abc
+
+class Shape(abc.ABC):
+    @property
+    def area(self):
+        return 0
+
+    @classmethod
+    def unit(cls):
+        return cls()
+
+    @staticmethod
+    def origin():
+        return (0, 0)
+
+    @abc.abstractmethod
+    def draw(self):
+        pass
+
+    @staticmethod
+    @abc.abstractmethod
+    def both():
+        pass
+
+    def plain(self):
+        pass
+";
+        let functions = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        let decorator = |name: &str| functions[name].decorator.as_deref();
+        assert_eq!(decorator("Shape.area"), Some("property"));
+        assert_eq!(decorator("Shape.unit"), Some("classmethod"));
+        assert_eq!(decorator("Shape.origin"), Some("staticmethod"));
+        assert_eq!(decorator("Shape.draw"), Some("abstractmethod"));
+        assert_eq!(decorator("Shape.both"), Some("staticmethod"));
+        assert_eq!(decorator("Shape.plain"), None);
+    }
+
+    #[test]
+    fn tabs_indent_python_to_multiples_of_eight() {
+        // The 8-space line is as deep as the tab-indented `inner()`, so it's back in `outer`.
+        let source = "def outer():\n\tdef inner():\n\t\thelper()\n\tinner()\n        helper()\n\ndef helper():\n\tpass\n";
+        let functions = parse_source(source, &Language::Py, &Config::default()).unwrap();
+        assert_eq!(callee_names(&functions["inner"]), ["helper"]);
+        let mut outer = callee_names(&functions["outer"]);
+        outer.sort();
+        assert_eq!(outer, ["helper", "inner"]);
+    }
+
+    #[test]
+    fn rust_blocks_end_at_balanced_braces_whatever_the_indentation() {
+        let source = "\
+mod shapes {
+pub struct Square;
+\t\timpl Square {
+  pub fn area(&self) -> u32 {
+self.side()
+\t}
+        fn side(&self) -> u32 { 1 }
+}
+}
+
+fn main() {
+    helper();
+}
+fn helper() {}
+";
+        let functions = parse_source(source, &Language::Rs, &Config::default()).unwrap();
+        assert_eq!(callee_names(&functions["shapes::Square::area"]), ["shapes::Square::side"]);
+        assert!(functions.contains_key("shapes::Square::side"));
+        assert_eq!(callee_names(&functions["main"]), ["helper"]);
+        assert!(functions["helper"].callees.is_empty());
+    }
+
+    /// The corpus under `tests/fixtures/corpus` parsed as a directory, against JSON the parser
+    /// wrote for it before it streamed files (`tests/fixtures/golden`), updated only where a fix
+    /// since changed the output on purpose.
+    #[test]
+    fn streaming_parse_matches_the_golden_output() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+        let configs = [
+            ("default", Config::default()),
+            ("references", Config { include_references: true, include_external: true, ignore_case_calls: true, ..Config::default() }),
+            ("edges_only", Config { edges_only: true, exclude_tests: true, definition_only_edges: true, ..Config::default() }),
+        ];
+        for (name, config) in configs {
+            let functions: HashMap<String, FnInfo> =
+                parse_directory(&dir, &config).unwrap().into_iter().map(|(name, info)| (name, info.normalized())).collect();
+            let mut out = Vec::new();
+            crate::output::write_json(&mut out, &functions, true).unwrap();
+            let json = String::from_utf8(out).unwrap().replace(&format!("{}/", dir.display()), "");
+            let golden = dir.with_file_name("golden").join(format!("{}.json", name));
+            assert_eq!(json, std::fs::read_to_string(golden).unwrap(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn references_survive_qualification() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/references");
+        let config = Config { include_references: true, ..Config::default() };
+        let functions = parse_directory(&dir, &config).unwrap();
+        assert!(functions["setup"].references.contains("a.py::helper"));
+        assert!(functions["main"].references.is_empty());
+        // `main` calls it; `setup` only passes it along.
+        assert_eq!(crate::fan_counts(&functions)["a.py::helper"], (1, 0));
+    }
 }
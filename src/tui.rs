@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+use crate::Location;
+use crate::graph::CallGraph;
+
+/// One visible line of the tree: the chain of names from its root down to this node.
+struct Row {
+    path: Vec<String>,
+    expandable: bool,
+    /// The node already appears among its ancestors, so it is never expanded.
+    cyclic: bool,
+}
+
+/// What the tree is currently rooted at, kept on a stack so jumps can be undone.
+struct View {
+    title: String,
+    roots: Vec<String>,
+    expanded: HashSet<Vec<String>>,
+    selected: usize,
+}
+
+struct App<'a> {
+    graph: &'a CallGraph<'a>,
+    location: &'a Location,
+    view: View,
+    history: Vec<View>,
+    rows: Vec<Row>,
+    offset: usize,
+}
+
+impl<'a> App<'a> {
+    fn new(graph: &'a CallGraph<'a>, location: &'a Location) -> Self {
+        let view = View {
+            title: "roots".to_string(),
+            roots: graph.roots(),
+            expanded: HashSet::new(),
+            selected: 0,
+        };
+        let mut app = Self { graph, location, view, history: Vec::new(), rows: Vec::new(), offset: 0 };
+        app.rebuild();
+        app
+    }
+
+    fn rebuild(&mut self) {
+        let mut rows = Vec::new();
+        for root in &self.view.roots {
+            self.flatten(vec![root.clone()], &mut rows);
+        }
+        self.rows = rows;
+        self.view.selected = self.view.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn flatten(&self, path: Vec<String>, rows: &mut Vec<Row>) {
+        let name = &path[path.len() - 1];
+        let cyclic = path[..path.len() - 1].contains(name);
+        let callees = self.graph.callees(name);
+        let expand = !cyclic && self.view.expanded.contains(&path);
+
+        rows.push(Row { path: path.clone(), expandable: !cyclic && !callees.is_empty(), cyclic });
+        if expand {
+            for callee in callees {
+                let mut child = path.clone();
+                child.push(callee.to_string());
+                self.flatten(child, rows);
+            }
+        }
+    }
+
+    fn selected_row(&self) -> Option<&Row> {
+        self.rows.get(self.view.selected)
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let last = self.rows.len().saturating_sub(1) as isize;
+        self.view.selected = (self.view.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    fn expand(&mut self) {
+        let Some(row) = self.selected_row() else { return };
+        if !row.expandable {
+            return;
+        }
+        let path = row.path.clone();
+        if self.view.expanded.insert(path) {
+            self.rebuild();
+        } else {
+            self.move_by(1);
+        }
+    }
+
+    fn collapse(&mut self) {
+        let Some(row) = self.selected_row() else { return };
+        let path = row.path.clone();
+        if self.view.expanded.remove(&path) {
+            self.rebuild();
+        } else if path.len() > 1 {
+            let parent = &path[..path.len() - 1];
+            if let Some(idx) = self.rows.iter().position(|row| row.path == parent) {
+                self.view.selected = idx;
+            }
+        }
+    }
+
+    fn jump(&mut self, title: String, roots: Vec<String>, expand_roots: bool) {
+        let expanded = if expand_roots {
+            roots.iter().map(|root| vec![root.clone()]).collect()
+        } else {
+            HashSet::new()
+        };
+        let next = View { title, roots, expanded, selected: 0 };
+        self.history.push(std::mem::replace(&mut self.view, next));
+        self.offset = 0;
+        self.rebuild();
+    }
+
+    fn jump_to_callers(&mut self) {
+        let Some(row) = self.selected_row() else { return };
+        let name = row.path[row.path.len() - 1].clone();
+        let callers = self.graph.callers(&name).iter().map(|s| s.to_string()).collect();
+        self.jump(format!("callers of {}", name), callers, false);
+    }
+
+    fn jump_to_callees(&mut self) {
+        let Some(row) = self.selected_row() else { return };
+        let name = row.path[row.path.len() - 1].clone();
+        self.jump(format!("calls from {}", name), vec![name], true);
+    }
+
+    fn back(&mut self) {
+        if let Some(previous) = self.history.pop() {
+            self.view = previous;
+            self.offset = 0;
+            self.rebuild();
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        let body_height = (height as usize).saturating_sub(2).max(1);
+
+        if self.view.selected < self.offset {
+            self.offset = self.view.selected;
+        } else if self.view.selected >= self.offset + body_height {
+            self.offset = self.view.selected + 1 - body_height;
+        }
+
+        queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+        let header = format!("pars: {} ({} rows)", self.view.title, self.rows.len());
+        queue!(out, SetAttribute(Attribute::Bold), Print(truncate(&header, width)), SetAttribute(Attribute::Reset))?;
+
+        for (screen_row, (idx, row)) in self.rows.iter().enumerate().skip(self.offset).take(body_height).enumerate() {
+            let name = &row.path[row.path.len() - 1];
+            let marker = match (row.expandable, self.view.expanded.contains(&row.path)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            let location = self.graph.get(name).map_or_else(String::new, |info| self.location.of(info));
+            let text = format!(
+                "{}{}{} ({}){}",
+                "  ".repeat(row.path.len() - 1),
+                marker,
+                name,
+                location,
+                if row.cyclic { " [cycle]" } else { "" }
+            );
+
+            queue!(out, cursor::MoveTo(0, screen_row as u16 + 1))?;
+            if idx == self.view.selected {
+                queue!(out, SetAttribute(Attribute::Reverse), Print(truncate(&text, width)), SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, Print(truncate(&text, width)))?;
+            }
+        }
+
+        let help = "↑↓ move  → expand  ← collapse  c callers  o callees  esc back  q quit";
+        queue!(out, cursor::MoveTo(0, height.saturating_sub(1)), Print(truncate(help, width)))?;
+        out.flush()
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Restores the terminal even if drawing fails part-way.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the interactive tree browser until the user quits.
+pub fn run(graph: &CallGraph, location: &Location) -> io::Result<()> {
+    let _guard = TerminalGuard::enter()?;
+    let mut app = App::new(graph, location);
+    let mut out = io::stdout();
+
+    loop {
+        app.draw(&mut out)?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => app.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.move_by(1),
+            KeyCode::PageUp => app.move_by(-10),
+            KeyCode::PageDown => app.move_by(10),
+            KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => app.expand(),
+            KeyCode::Left | KeyCode::Char('h') => app.collapse(),
+            KeyCode::Char('c') => app.jump_to_callers(),
+            KeyCode::Char('o') => app.jump_to_callees(),
+            KeyCode::Esc | KeyCode::Backspace => app.back(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,5 @@
+﻿def main():
+    helper()
+
+def helper():
+    pass
@@ -0,0 +1,50 @@
+#import "Shape.h"
+
+static int counter = 0;
+
+@implementation Shape
+
++ (instancetype)shapeWithWidth:(double)width height:(double)height {
+    Shape *shape = [[Shape alloc] initWithWidth:width height:height];
+    [shape log:@"made: %d", 1];
+    return shape;
+}
+
+- (instancetype)initWithWidth:(double)width height:(double)height {
+    self = [super init];
+    if (self) {
+        _width = width;
+        _height = height;
+    }
+    return self;
+}
+
+- (double)area {
+    return [self width] * _height;
+}
+
+- (double)width { return _width; }
+
+- (void)moveBy:(double)dx
+            dy:(double)dy
+{
+    [self resetWithValue:[self area]
+                    flag:YES];
+}
+
+- (void)resetWithValue:(double)value flag:(BOOL)flag {
+    if (flag) {
+        [self moveBy:value dy:-value];
+    }
+}
+
+- (void)log:(NSString *)format, ... {
+    NSLog(@"%@", format);
+}
+
+- (void)unused {
+    [self area];
+    SEL s = @selector(moveBy:dy:);
+}
+
+@end
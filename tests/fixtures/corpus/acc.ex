@@ -0,0 +1,38 @@
+defmodule MyApp.Accounts do
+  @moduledoc "Accounts"
+
+  def create(attrs) do
+    attrs
+    |> validate()
+    |> save!()
+  end
+
+  defp validate(attrs) do
+    Enum.map(attrs, fn x -> check?(x) end)
+  end
+
+  defp check?(x), do: x != nil
+
+  def save!(attrs) do
+    if valid?(attrs) do
+      attrs
+    end
+  end
+
+  @doc "after"
+  def valid? do
+    helper()
+  end
+
+  defp do_helper(x) do
+    x
+  end
+
+  def helper(), do: do_helper(1)
+end
+
+defmodule Other do
+  def create(x) do
+    MyApp.Accounts.create(x)
+  end
+end
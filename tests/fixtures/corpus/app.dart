@@ -0,0 +1,52 @@
+import 'package:flutter/material.dart';
+
+void main() {
+  runApp(const MyApp());
+}
+
+int add(int a, int b) => a + b;
+
+Map<String, int> counts(List<String> words) {
+  final result = <String, int>{};
+  for (final w in words) {
+    result[w] = add(result[w] ?? 0, 1);
+  }
+  return result;
+}
+
+class MyApp extends StatelessWidget {
+  const MyApp({super.key});
+
+  @override
+  Widget build(BuildContext context) {
+    return MaterialApp(home: helper('{'));
+  }
+
+  String helper(String s) => describe(s);
+
+  static String describe(String s) =>
+      s.toUpperCase() + format(s);
+
+  MyApp.named(int x) : this.count = x {
+    init();
+  }
+
+  void init() {
+    setState(() {
+      counts([]);
+    });
+  }
+}
+
+String format(String s) {
+  if (s.isEmpty) {
+    return '';
+  }
+  return s;
+}
+
+Future<void> load() async {
+  await fetch();
+}
+
+Future<void> fetch() async {}
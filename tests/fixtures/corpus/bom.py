@@ -0,0 +1,5 @@
+﻿def first():
+    second()
+
+def second():
+    pass
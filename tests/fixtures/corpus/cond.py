@@ -0,0 +1,20 @@
+import sys
+
+if sys.platform == "win32":
+    def path_sep():
+        return helper("\\")
+    def home():
+        return path_sep()
+    X = compute()
+else:
+    def path_sep():
+        return helper("/")
+
+def helper(x):
+    return x
+
+def compute():
+    def inner():
+        return helper(1)
+    value = inner()
+    return helper(value)
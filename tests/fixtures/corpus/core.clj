@@ -0,0 +1,18 @@
+(ns app.core
+  (:require [clojure.string :as str]))
+
+;; helpers
+(defn- valid? [x]
+  (some? x))
+
+(defn add [a b] (+ a b))
+
+(defn ^:private total
+  "Sums xs."
+  [xs]
+  (reduce add 0 (filter valid? xs)))
+
+(defn -main [& args]
+  (println (total [1 2 3]))
+  (app.core/add 1 2)
+  (str/join "," args))
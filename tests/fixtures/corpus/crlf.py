@@ -0,0 +1,5 @@
+def main():
+    run()
+
+def run():
+    pass
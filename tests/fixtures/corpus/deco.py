@@ -0,0 +1,32 @@
+import abc
+
+class Shape:
+    @property
+    def area(self):
+        return compute(self)
+
+    @classmethod
+    def unit(cls):
+        return cls()
+
+    @staticmethod
+    def origin():
+        return make_point()
+
+    @staticmethod
+    @abc.abstractmethod
+    def describe():
+        pass
+
+    @abc.abstractmethod
+    def draw(self):
+        pass
+
+    def plain(self):
+        return self.area
+
+def compute(shape):
+    return 1
+
+def make_point():
+    return 0
@@ -0,0 +1,16 @@
+def helper():
+    return 1
+
+def other():
+    return 2
+
+def f(x=helper()):
+    return x
+
+def g(
+    a,
+    b=other(),
+):
+    return a
+
+def h(x=helper()): return other()
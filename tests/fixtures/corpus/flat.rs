@@ -0,0 +1,32 @@
+mod util {
+fn inner() {
+    leaf();
+}
+
+#[inline]
+fn leaf() {}
+}
+
+impl Foo {
+pub fn new() -> Foo {
+	Foo::build()
+}
+
+#[doc = "x"]
+fn build() -> Foo {
+	util::inner();
+	Foo
+}
+}
+
+#[cfg(test)]
+mod tests {
+#[test]
+fn check() {
+    Foo::new();
+}
+}
+
+fn main() {
+	Foo::new();
+}
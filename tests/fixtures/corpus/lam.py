@@ -0,0 +1,9 @@
+def process(x):
+    return x
+
+handler = lambda x: process(x)
+
+def main():
+    key = lambda y: y
+    handler(1)
+    key(2)
@@ -0,0 +1,23 @@
+#!/usr/bin/perl
+use strict;
+
+sub helper;
+
+sub main {
+    my $x = helper(1);
+    &util::log($x);
+}
+
+package util;
+
+sub log {
+    print @_;
+}
+
+sub helper($) {
+    log("h");
+    return $_[0];
+}
+
+package main;
+main();
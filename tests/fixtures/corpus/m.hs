@@ -0,0 +1,22 @@
+{-# LANGUAGE OverloadedStrings #-}
+module Main where
+
+import Data.List (sort)
+
+-- | Factorial
+fact :: Int -> Int
+fact 0 = 1
+fact n = n * fact (n - 1)
+
+x <+> y = x ++ y
+
+render :: [Int] -> String
+render xs = unwords (map show' xs)
+  where
+    show' n = show (fact n)
+
+main :: IO ()
+main = do
+  let xs = sort [3, 1, 2]
+  putStrLn (render xs)
+  print (fact 5)
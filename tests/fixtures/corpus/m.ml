@@ -0,0 +1,21 @@
+(* helpers *)
+let x = 5
+let limit : int = 10
+
+let square n = n * n
+
+let rec sum_squares = function
+  | [] -> 0
+  | h :: t -> square h + sum_squares t
+
+let run () =
+  let total = sum_squares [1; 2; 3] in
+  print_int total;
+  log_it total
+
+let log_it v =
+  Printf.printf "%d\n" v
+
+let (+!) a b = a + b
+
+let () = run ()
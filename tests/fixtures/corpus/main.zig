@@ -0,0 +1,16 @@
+const std = @import("std");
+
+fn helper(x: u32) u32 {
+    return x + 1;
+}
+
+pub fn main() !void {
+    const v = helper(1);
+    try Point.init(v);
+}
+
+const Point = struct {
+    pub fn init(v: u32) !void {
+        _ = v;
+    }
+};
@@ -0,0 +1,36 @@
+mod net {
+    pub fn connect() {
+        helper();
+    }
+
+    fn helper() {}
+
+    pub mod tls {
+        pub struct Conn;
+
+        impl Conn {
+            fn open() {
+                handshake();
+            }
+        }
+
+        fn handshake() {}
+    }
+}
+
+mod db;
+
+fn connect() {
+    net::connect();
+}
+
+fn main() {
+    connect();
+}
+
+#[cfg(test)]
+mod tests {
+    fn test_it() {
+        main();
+    }
+}
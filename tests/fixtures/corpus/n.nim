@@ -0,0 +1,18 @@
+import strutils
+
+proc square(x: int): int =
+  x * x
+
+func sumSquares*(xs: seq[int]): int =
+  # comment: square(0)
+  for x in xs:
+    result += square(x)
+
+proc show[T](v: T) =
+  echo $v
+
+proc main() =
+  let total = sumSquares(@[1, 2, 3])
+  show(total)
+
+main()
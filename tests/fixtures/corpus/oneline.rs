@@ -0,0 +1,2 @@
+fn a() { b(); } pub fn b() { c(); } pub(crate) fn c() {}
+fn main() { a(); let s = "fn x() {}"; }
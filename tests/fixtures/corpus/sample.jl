@@ -0,0 +1,38 @@
+module Geo
+
+using LinearAlgebra
+import Base: show
+
+# helpers
+square(x) = x * x
+norm2(v::Vector)::Float64 = sum(square(x) for x in v)
+scale(v, k) where {T} = [k * x for x in v]
+
+function distance(a, b)
+    d = a - b
+    if length(d) > 0
+        return sqrt(norm2(d))
+    end
+    last = d[end]
+    helper!(d)
+    total = 0
+    for x in d
+        total += square(x)
+    end
+    total
+end
+
+function helper!(v)
+    map(v) do x
+        square(x)
+    end
+    push!(v, 1)
+end
+
+function Base.show(io::IO, p)
+    print(io, distance(p, p))
+end
+
+main() = distance([1.0], [2.0])
+
+end
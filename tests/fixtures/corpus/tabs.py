@@ -0,0 +1,14 @@
+class A:
+	def run(self):
+		self.helper()
+
+	def helper(self):
+		if True:
+			free()
+
+def free():
+	pass
+
+def main():
+    A().run()
+	free()
@@ -0,0 +1,9 @@
+def outer():
+    def inner():
+        helper()
+    inner()
+    helper()
+    helper()
+
+def helper():
+    pass
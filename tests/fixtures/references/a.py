@@ -0,0 +1,9 @@
+def helper():
+    pass
+
+def main():
+    register(helper)
+    helper()
+
+def setup():
+    register(helper)
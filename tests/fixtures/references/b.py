@@ -0,0 +1,5 @@
+def helper():
+    pass
+
+def register(callback):
+    callback()
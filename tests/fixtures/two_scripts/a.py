@@ -0,0 +1,6 @@
+from b import helper
+
+def test_thing():
+    shared()
+
+print(helper())
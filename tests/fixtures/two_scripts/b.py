@@ -0,0 +1,7 @@
+def helper():
+    return 1
+
+def shared():
+    return 2
+
+helper()